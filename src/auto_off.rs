@@ -0,0 +1,166 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Timed auto-off output
+//!
+//! Outputs driving motors, relays or similar actuators should not stay energized if the control loop driving them
+//! crashes or hangs. The [AutoOff] wrapper drives an output pin active and expects a regular [AutoOff::keepalive].
+//! If the keepalive is missing for longer than the configured timeout the pin is driven inactive on the next
+//! [AutoOff::poll].
+//!
+//! The time source is injected by passing the current time to the functions. The unit of the time values (e.g.
+//! microseconds of the system timer) is up to the caller, it only needs to be the same for all values passed.
+//!
+
+use crate::{function, Pin};
+
+/// Wrapper of an output [Pin] that is driven inactive (low) once a deadline has passed without any keepalive. The pin
+/// is also driven inactive when the wrapper is dropped.
+pub struct AutoOff<PUD> {
+  pin: Pin<function::Output, PUD>,
+  deadline: Deadline,
+}
+
+impl<PUD> AutoOff<PUD> {
+  /// Take ownership of the output pin, drive it active (high) and set the deadline to ``now + timeout``.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn now() -> u64 { 0 }
+  /// # fn doc() {
+  /// let pin = GPIO.with_mut(|gpio| gpio.get_pin(17).unwrap().into_output());
+  /// // the relay connected to the pin is switched off if there is no keepalive within 500ms
+  /// let mut relay = AutoOff::new(pin, 500_000, now());
+  /// loop {
+  ///     relay.keepalive(now());
+  ///     relay.poll(now());
+  /// }
+  /// # }
+  /// ```
+  pub fn new(pin: Pin<function::Output, PUD>, timeout: u64, now: u64) -> Self {
+    let mut auto_off = Self {
+      pin,
+      deadline: Deadline::new(timeout),
+    };
+    auto_off.activate(now);
+    auto_off
+  }
+
+  /// Drive the pin active (high) and set the deadline to ``now + timeout``
+  pub fn activate(&mut self, now: u64) {
+    self.deadline.start(now);
+    self.pin.high();
+  }
+
+  /// Drive the pin inactive (low) immediately
+  pub fn deactivate(&mut self) {
+    self.deadline.stop();
+    self.pin.low();
+  }
+
+  /// Extend the deadline of an active pin to ``now + timeout``. A pin that has already been driven inactive is not
+  /// re-activated by the keepalive, this requires an explicit call to [AutoOff::activate].
+  pub fn keepalive(&mut self, now: u64) {
+    self.deadline.extend(now);
+  }
+
+  /// Check the deadline and drive the pin inactive (low) if it has passed. Returns whether the pin is still active.
+  pub fn poll(&mut self, now: u64) -> bool {
+    if self.deadline.passed(now) {
+      self.deactivate();
+    }
+    self.is_active()
+  }
+
+  /// Returns whether the pin is currently driven active
+  pub fn is_active(&self) -> bool {
+    self.deadline.is_running()
+  }
+}
+
+impl<PUD> Drop for AutoOff<PUD> {
+  fn drop(&mut self) {
+    // ensure the fail-safe level when the wrapper goes away
+    self.pin.low();
+  }
+}
+
+/// The deadline of an [AutoOff] pin. It is only running while the pin is active.
+struct Deadline {
+  timeout: u64,
+  at: Option<u64>,
+}
+
+impl Deadline {
+  const fn new(timeout: u64) -> Self {
+    Self { timeout, at: None }
+  }
+
+  /// Start the deadline at ``now + timeout``
+  fn start(&mut self, now: u64) {
+    self.at = Some(now.saturating_add(self.timeout));
+  }
+
+  fn stop(&mut self) {
+    self.at = None;
+  }
+
+  /// Move a running deadline to ``now + timeout``, a stopped deadline stays stopped
+  fn extend(&mut self, now: u64) {
+    if self.at.is_some() {
+      self.start(now);
+    }
+  }
+
+  /// Whether the deadline is running and has passed
+  fn passed(&self, now: u64) -> bool {
+    self.at.map_or(false, |at| now >= at)
+  }
+
+  fn is_running(&self) -> bool {
+    self.at.is_some()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn deadline_passes_after_timeout() {
+    let mut deadline = Deadline::new(100);
+    deadline.start(1_000);
+    assert!(!deadline.passed(1_099));
+    assert!(deadline.passed(1_100));
+  }
+
+  #[test]
+  fn keepalive_extends_a_running_deadline() {
+    let mut deadline = Deadline::new(100);
+    deadline.start(1_000);
+    deadline.extend(1_050);
+    assert!(!deadline.passed(1_100));
+    assert!(deadline.passed(1_150));
+  }
+
+  #[test]
+  fn keepalive_does_not_restart_a_stopped_deadline() {
+    let mut deadline = Deadline::new(100);
+    deadline.start(1_000);
+    deadline.stop();
+    deadline.extend(1_050);
+    assert!(!deadline.is_running());
+    assert!(!deadline.passed(u64::MAX));
+  }
+
+  #[test]
+  fn deadline_saturates() {
+    let mut deadline = Deadline::new(100);
+    deadline.start(u64::MAX - 10);
+    assert!(!deadline.passed(u64::MAX - 1));
+    assert!(deadline.passed(u64::MAX));
+  }
+}
@@ -0,0 +1,124 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Board revision
+//!
+//! The pinout of the GPIO header changed between the early revisions of the Raspberry Pi. The board revision code
+//! (e.g. read from the mailbox property interface) tells which pins are wired to the header and therefore which
+//! peripheral is available there.
+//!
+//! # Example
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! # fn doc() {
+//! // the revision code as reported by the firmware
+//! let revision = board::BoardRevision::new(0xa02082);
+//! assert_eq!(revision.model(), Some(PiModel::Pi3));
+//! let (sda, scl) = peripheral::header_i2c(revision);
+//! # }
+//! ```
+
+use crate::PiModel;
+
+/// The board revision code of a Raspberry Pi
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardRevision(u32);
+
+impl BoardRevision {
+  /// Create the board revision from the revision code reported by the firmware
+  pub const fn new(code: u32) -> Self {
+    BoardRevision(code)
+  }
+
+  /// The raw revision code
+  pub const fn code(&self) -> u32 {
+    self.0
+  }
+
+  /// Whether the revision code uses the new style encoding introduced with the Raspberry Pi 2
+  pub const fn is_new_style(&self) -> bool {
+    self.0 & (1 << 23) != 0
+  }
+
+  /// Whether this is a first revision Raspberry Pi 1 Model B. Those have the I2C0 bus at the GPIO
+  /// header instead of the I2C1 bus available there on all later boards.
+  pub const fn is_pi1_rev1(&self) -> bool {
+    // the revision codes might have the over-voltage bit (24) set
+    let code = self.0 & 0x00FF_FFFF;
+    !self.is_new_style() && (code == 0x0002 || code == 0x0003)
+  }
+
+  /// The Raspberry Pi model of this board revision. Returns ``None`` for unknown revision codes
+  pub const fn model(&self) -> Option<PiModel> {
+    if !self.is_new_style() {
+      // all old style revision codes belong to the Raspberry Pi 1 and compute module 1
+      return match self.0 & 0x00FF_FFFF {
+        0x0002..=0x0015 => Some(PiModel::Pi1),
+        _ => None,
+      };
+    }
+    match (self.0 >> 4) & 0xFF {
+      0x00 | 0x01 | 0x02 | 0x03 | 0x06 => Some(PiModel::Pi1),
+      0x04 => Some(PiModel::Pi2),
+      0x08 | 0x0A | 0x0D | 0x0E | 0x10 => Some(PiModel::Pi3),
+      0x09 | 0x0C | 0x12 => Some(PiModel::Zero),
+      0x11 | 0x13 => Some(PiModel::Pi4),
+      0x14 => Some(PiModel::Cm4),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::peripheral::{header_i2c, i2c0_scl, i2c0_sda, i2c1_scl, i2c1_sda};
+
+  #[test]
+  fn old_style_codes_are_pi1() {
+    assert_eq!(BoardRevision::new(0x0002).model(), Some(PiModel::Pi1));
+    assert_eq!(BoardRevision::new(0x000e).model(), Some(PiModel::Pi1));
+    assert_eq!(BoardRevision::new(0x0015).model(), Some(PiModel::Pi1));
+    assert_eq!(BoardRevision::new(0x0016).model(), None);
+  }
+
+  #[test]
+  fn new_style_codes_give_the_model() {
+    assert_eq!(BoardRevision::new(0xa01041).model(), Some(PiModel::Pi2));
+    assert_eq!(BoardRevision::new(0xa02082).model(), Some(PiModel::Pi3));
+    assert_eq!(BoardRevision::new(0xa020d3).model(), Some(PiModel::Pi3));
+    assert_eq!(BoardRevision::new(0x9000c1).model(), Some(PiModel::Zero));
+    assert_eq!(BoardRevision::new(0xa03111).model(), Some(PiModel::Pi4));
+    assert_eq!(BoardRevision::new(0xa03140).model(), Some(PiModel::Cm4));
+    assert_eq!(BoardRevision::new(0xa020f0).model(), None);
+  }
+
+  #[test]
+  fn only_the_first_pi1_revision_is_rev1() {
+    assert!(BoardRevision::new(0x0002).is_pi1_rev1());
+    assert!(BoardRevision::new(0x0003).is_pi1_rev1());
+    // over-voltage bit set
+    assert!(BoardRevision::new(0x0100_0003).is_pi1_rev1());
+    assert!(!BoardRevision::new(0x0004).is_pi1_rev1());
+    assert!(!BoardRevision::new(0xa02082).is_pi1_rev1());
+  }
+
+  #[test]
+  fn header_i2c_follows_the_revision() {
+    assert_eq!(
+      header_i2c(BoardRevision::new(0x0002)),
+      (i2c0_sda(), i2c0_scl())
+    );
+    assert_eq!(
+      header_i2c(BoardRevision::new(0x000e)),
+      (i2c1_sda(), i2c1_scl())
+    );
+    assert_eq!(
+      header_i2c(BoardRevision::new(0xa02082)),
+      (i2c1_sda(), i2c1_scl())
+    );
+  }
+}
@@ -0,0 +1,112 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Bus definition
+//!
+//! Declarative definition of a parallel bus on top of a [Port](crate::Port). Each line of the bus is given a name and
+//! the position of its pin within the GPIO bank. The order of the lines defines the bit order of the values written
+//! to and read from the bus.
+//!
+
+/// Define a parallel bus with named lines. This generates a type wrapping the [Port](crate::Port) of the bus pins that
+/// translates the values written to or read from the bus into the pin positions of the lines. The first line is bit
+/// ``0`` of the value.
+/// The pin positions are given within the GPIO bank. Using the same position twice or a position outside the bank is
+/// rejected while compiling.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// bus! {
+///   /// 8-bit data bus of a display
+///   pub struct DataBus: Bank0 {
+///     d0 = 2, d1 = 3, d2 = 4, d3 = 17, d4 = 27, d5 = 22, d6 = 10, d7 = 9
+///   }
+/// }
+///
+/// # fn doc() {
+/// let bus = GPIO.with_mut(|gpio| DataBus::acquire(gpio)).unwrap();
+/// bus.port().enable_output();
+/// bus.write(0xA5);
+/// // pulse a single line of the bus
+/// bus.port().set(DataBus::d7);
+/// bus.port().clear(DataBus::d7);
+/// let value = bus.read();
+/// # }
+/// ```
+#[macro_export]
+macro_rules! bus {
+  ($(#[$meta:meta])* $vis:vis struct $bus:ident: $bank:ident { $($line:ident = $pos:expr),+ $(,)? }) => {
+    $(#[$meta])*
+    $vis struct $bus {
+      port: $crate::Port,
+    }
+
+    // each pin position must be unique, otherwise the enum discriminants would clash
+    const _: () = {
+      #[allow(dead_code, non_camel_case_types)]
+      enum UniqueLines {
+        $($line = $pos),+
+      }
+    };
+    // each pin position must be within the bank, bank 1 only has 22 pins
+    $(
+      const _: [(); 0] = [(); ($pos >= $crate::GpioBank::$bank.pin_count()) as usize];
+    )+
+
+    #[allow(dead_code, non_upper_case_globals)]
+    impl $bus {
+      $(
+        /// The bit mask of this line within the GPIO bank
+        pub const $line: u32 = 1 << $pos;
+      )+
+
+      /// The pin positions of the lines within the GPIO bank in the bit order of the bus
+      const LINES: &'static [u32] = &[$($pos),+];
+
+      /// Acquire all pins of the bus. Returns an Err if any of them is already in use, in this case no pin is
+      /// acquired.
+      pub fn acquire(gpio: &mut $crate::Gpio) -> Result<Self, $crate::GpioError> {
+        let mask = 0 $(| (1 << $pos))+;
+        gpio
+          .get_port($crate::GpioBank::$bank, mask)
+          .map(|port| Self { port })
+      }
+
+      /// Release all pins of the bus
+      pub fn release(self, gpio: &mut $crate::Gpio) {
+        gpio.free_port(self.port);
+      }
+
+      /// The ``Port`` containing all pins of the bus
+      pub fn port(&self) -> &$crate::Port {
+        &self.port
+      }
+
+      /// Drive the lines of the bus to the value given. Bit ``0`` of the value is the first line.
+      pub fn write(&self, value: u32) {
+        let mut levels = 0;
+        for (bit, pos) in Self::LINES.iter().enumerate() {
+          if value & (1 << bit) != 0 {
+            levels |= 1 << pos;
+          }
+        }
+        self.port.write(levels);
+      }
+
+      /// Read the value of the bus. Bit ``0`` of the value is the first line.
+      pub fn read(&self) -> u32 {
+        let levels = self.port.read();
+        let mut value = 0;
+        for (bit, pos) in Self::LINES.iter().enumerate() {
+          if levels & (1 << pos) != 0 {
+            value |= 1 << bit;
+          }
+        }
+        value
+      }
+    }
+  };
+}
@@ -0,0 +1,252 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Event coalescing
+//!
+//! Bound the frequency of the GPIO bank interrupts for noisy or high-frequency inputs. After a bank interrupt has been
+//! handled the interrupt of this bank is held back for a configurable window. The event detection stays armed, so the
+//! events detected within the window are latched in the event detect status and dispatched together once the window
+//! has passed and the bank interrupt fires again. The event detection and the event handlers are not touched, so
+//! handlers registered or removed within the window are in place once it ends.
+//!
+//! The end of the window is signalled by the interrupt of the compare channel 1 of the system timer, one of the two
+//! channels not used by the GPU. The interrupt handlers do not wait for the window to pass.
+//!
+
+use crate::interface::*;
+#[cfg(feature = "mock-mmio")]
+use crate::mock::irq;
+use crate::{hold_bank_interrupt, is_bank_interrupt_held, release_bank_interrupt, Gpio};
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(not(feature = "mock-mmio"))]
+use ruspiro_interrupt as irq;
+use ruspiro_interrupt::{Interrupt, IrqHandler, IsrSender};
+
+/// the event coalescing window in micro seconds, ``0`` if the event coalescing is disabled
+static WINDOW_US: AtomicU32 = AtomicU32::new(0);
+/// the system timer counter value the window of the GPIO bank 0 and 1 ends at
+static WINDOW_END: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+
+impl Gpio {
+  /// Configure the event coalescing window of the GPIO bank interrupts in micro seconds. Once set to a value other
+  /// than ``0`` the interrupt of a bank is held back for the given window after each bank interrupt has been handled.
+  /// This bounds the frequency the bank interrupt could fire with for noisy or high-frequency inputs. The events
+  /// detected within the window are not lost but dispatched together once the window has passed, each pin at most
+  /// once. The price is the latency: an event is dispatched up to a whole window after it occured. So keep the window
+  /// as small as possible.
+  /// A value of ``0`` disables the event coalescing, which is the default.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// // at most one interrupt per bank every 500µs
+  /// GPIO.with_mut(|gpio| gpio.set_event_coalescing(500));
+  /// # }
+  /// ```
+  pub fn set_event_coalescing(&mut self, window_us: u32) {
+    WINDOW_US.store(window_us, Ordering::Release);
+  }
+
+  /// Get the currently configured event coalescing window in micro seconds. A value of ``0`` means the event
+  /// coalescing is disabled
+  pub fn event_coalescing(&self) -> u32 {
+    WINDOW_US.load(Ordering::Acquire)
+  }
+}
+
+/// Hold back the interrupt of the given bank for the configured event coalescing window. This is called once the
+/// bank interrupt has been handled and returns right away, the interrupt is released by the system timer.
+pub(crate) fn coalesce_events(bank: GpioBank) {
+  let window = WINDOW_US.load(Ordering::Acquire);
+  if window != 0 {
+    WINDOW_END[bank as usize].store(
+      SYS_TIMER_CLO::Register.get().wrapping_add(window),
+      Ordering::Release,
+    );
+    hold_bank_interrupt(bank);
+    schedule_window_end();
+  }
+}
+
+/// Release the bank interrupts whose window has passed and let the compare channel 1 of the system timer fire at the
+/// earliest end of the windows still open
+fn schedule_window_end() {
+  loop {
+    let now = SYS_TIMER_CLO::Register.get();
+    let mut next: Option<u32> = None;
+    for &bank in [GpioBank::Bank0, GpioBank::Bank1].iter() {
+      if !is_bank_interrupt_held(bank) {
+        continue;
+      }
+      let end = WINDOW_END[bank as usize].load(Ordering::Acquire);
+      if has_passed(end, now) {
+        release_bank_interrupt(bank);
+      } else if next.map_or(true, |next| end.wrapping_sub(now) < next.wrapping_sub(now)) {
+        next = Some(end);
+      }
+    }
+    match next {
+      None => {
+        irq::deactivate(Interrupt::SystemTimer1);
+        return;
+      }
+      Some(end) => {
+        SYS_TIMER_C1::Register.set(end);
+        // a window ending while the compare is written would only match once the counter wrapped around
+        if !has_passed(end, SYS_TIMER_CLO::Register.get()) {
+          irq::activate(Interrupt::SystemTimer1, None);
+          return;
+        }
+      }
+    }
+  }
+}
+
+/// Whether the system timer counter has reached the given end of a window. The counter wraps around after about 71
+/// minutes, so the end is considered passed if it is not more than half of the counter range ahead of ``now``.
+const fn has_passed(end: u32, now: u32) -> bool {
+  end.wrapping_sub(now) == 0 || end.wrapping_sub(now) > u32::MAX / 2
+}
+
+/// Acknowledge the compare match of channel 1 and release the bank interrupts whose window has passed
+fn process_timer_interrupt() {
+  SYS_TIMER_CS::Register.set(1 << 1);
+  schedule_window_end();
+}
+
+/// Implement the interrupt handler of the system timer compare channel 1 ending the event coalescing window
+/// # Safety
+/// The handler only accesses the atomic state of the event coalescing and the system timer registers.
+#[IrqHandler(SystemTimer1)]
+unsafe fn handle_coalescing_window(tx: Option<IsrSender<Box<dyn Any>>>) {
+  process_timer_interrupt();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn window_end_passes_across_the_counter_wrap() {
+    assert!(!has_passed(100, 50));
+    assert!(has_passed(100, 100));
+    assert!(has_passed(100, 150));
+    assert!(!has_passed(10, u32::MAX - 10));
+    assert!(has_passed(u32::MAX - 10, 10));
+  }
+
+  #[cfg(feature = "mock-mmio")]
+  mod mocked {
+    use super::*;
+    use crate::{mock, GpioEvent};
+
+    const SYS_TIMER_CLO_ADDR: usize = SYS_TIMER_BASE + 0x04;
+
+    #[test]
+    fn bank_interrupt_is_held_back_for_the_window() {
+      static CALLS: AtomicU32 = AtomicU32::new(0);
+      let _test = crate::exclusive_test();
+      let mut gpio = Gpio::new();
+      gpio.set_event_coalescing(100);
+      let pin = gpio.get_pin(17).unwrap().into_input();
+      gpio
+        .register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || {
+          CALLS.fetch_add(1, Ordering::AcqRel);
+        })
+        .unwrap();
+      mock::poke(SYS_TIMER_CLO_ADDR, 1_000);
+      unsafe {
+        mock::trigger_event(17, GpioEvent::RisingEdge);
+        assert_eq!(CALLS.load(Ordering::Acquire), 1);
+        assert!(!mock::is_irq_active(Interrupt::GpioBank0));
+        assert!(mock::is_irq_active(Interrupt::SystemTimer1));
+        assert_eq!(mock::peek(SYS_TIMER_BASE + 0x10), 1_100);
+
+        // the event within the window stays latched
+        mock::set_level(17, false);
+        mock::trigger_event(17, GpioEvent::RisingEdge);
+        assert_eq!(CALLS.load(Ordering::Acquire), 1);
+        assert_eq!(mock::peek(GPIO_BASE + 0x40), 1 << 17);
+
+        mock::poke(SYS_TIMER_CLO_ADDR, 1_100);
+        process_timer_interrupt();
+        assert!(mock::is_irq_active(Interrupt::GpioBank0));
+        assert!(!mock::is_irq_active(Interrupt::SystemTimer1));
+        // the re-activated bank interrupt fires for the latched event
+        gpio.set_event_coalescing(0);
+        crate::process_bank_interrupt(GpioBank::Bank0);
+      }
+      assert_eq!(CALLS.load(Ordering::Acquire), 2);
+      gpio.remove_event_handler(&pin).unwrap();
+    }
+
+    #[test]
+    fn handlers_changed_within_the_window_stay_in_place() {
+      let _test = crate::exclusive_test();
+      let mut gpio = Gpio::new();
+      gpio.set_event_coalescing(100);
+      let first = gpio.get_pin(17).unwrap().into_input();
+      let second = gpio.get_pin(18).unwrap().into_input();
+      gpio
+        .register_recurring_event_handler(&first, GpioEvent::RisingEdge, || ())
+        .unwrap();
+      unsafe { mock::trigger_event(17, GpioEvent::RisingEdge) };
+      assert!(!mock::is_irq_active(Interrupt::GpioBank0));
+
+      // reconfigure the bank while its interrupt is held back
+      gpio.remove_event_handler(&first).unwrap();
+      gpio
+        .register_recurring_event_handler(&second, GpioEvent::FallingEdge, || ())
+        .unwrap();
+      gpio.with_interrupts_disabled(|_| ());
+      assert!(!mock::is_irq_active(Interrupt::GpioBank0));
+
+      mock::poke(SYS_TIMER_CLO_ADDR, 100);
+      process_timer_interrupt();
+      assert!(mock::is_irq_active(Interrupt::GpioBank0));
+      let expected = [0, 1 << 18, 0, 0, 0, 0];
+      assert_eq!(get_detect_enables(GpioBank::Bank0), expected);
+      gpio.set_event_coalescing(0);
+      gpio.remove_event_handler(&second).unwrap();
+    }
+
+    #[test]
+    fn windows_of_both_banks_end_in_order() {
+      let _test = crate::exclusive_test();
+      let mut gpio = Gpio::new();
+      gpio.set_event_coalescing(100);
+      let pins = [
+        gpio.get_pin(17).unwrap().into_input(),
+        gpio.get_pin(40).unwrap().into_input(),
+      ];
+      for pin in pins.iter() {
+        gpio
+          .register_recurring_event_handler(pin, GpioEvent::High, || ())
+          .unwrap();
+      }
+      coalesce_events(GpioBank::Bank0);
+      mock::poke(SYS_TIMER_CLO_ADDR, 50);
+      coalesce_events(GpioBank::Bank1);
+      assert_eq!(mock::peek(SYS_TIMER_BASE + 0x10), 100);
+
+      mock::poke(SYS_TIMER_CLO_ADDR, 100);
+      process_timer_interrupt();
+      assert!(mock::is_irq_active(Interrupt::GpioBank0));
+      assert!(!mock::is_irq_active(Interrupt::GpioBank1));
+      assert_eq!(mock::peek(SYS_TIMER_BASE + 0x10), 150);
+
+      mock::poke(SYS_TIMER_CLO_ADDR, 150);
+      process_timer_interrupt();
+      assert!(mock::is_irq_active(Interrupt::GpioBank1));
+      assert!(!mock::is_irq_active(Interrupt::SystemTimer1));
+      gpio.set_event_coalescing(0);
+      for pin in pins.iter() {
+        gpio.remove_event_handler(pin).unwrap();
+      }
+    }
+  }
+}
@@ -0,0 +1,182 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Configured pin
+//!
+//! A pin acquired and fully configured with a single call. The configuration is only known at runtime, so the pin
+//! does not carry it in its type like a [Pin](crate::Pin) does. This fits pins configured from e.g. a board
+//! description.
+//!
+
+use crate::interface::*;
+use crate::{Gpio, GpioError};
+
+/// A pin with a function and pull up/down setting only known at runtime. See [Gpio::get_pin_with_config].
+/// Like a [Pin](crate::Pin) it is released once dropped.
+pub struct ConfiguredPin {
+  num: u32,
+  function: Function,
+  pud: Pud,
+}
+
+impl ConfiguredPin {
+  /// The number of the pin
+  pub fn num(&self) -> u32 {
+    self.num
+  }
+
+  /// The function the pin has been configured for
+  pub fn function(&self) -> Function {
+    self.function
+  }
+
+  /// The pull up/down setting the pin has been configured with
+  pub fn pud(&self) -> Pud {
+    self.pud
+  }
+
+  /// Consume the pin and return its number. Other than dropping the pin it stays in use, so the
+  /// number could be handed to [Gpio::free_pin] to release it.
+  pub fn into_num(self) -> u32 {
+    let num = self.num;
+    // skip the release of the pin on drop
+    core::mem::forget(self);
+    crate::unclaim_pin(num);
+    num
+  }
+
+  /// Read the current level of the pin, ``true`` for high and ``false`` for low
+  pub fn is_high(&self) -> bool {
+    get_levels(pin_bank(self.num)) & (1 << (self.num & 31)) != 0
+  }
+
+  /// Drive the pin to the given level.
+  /// Returns an Err if the pin is not configured as output.
+  pub fn write(&self, level: bool) -> Result<(), GpioError> {
+    if self.function != Function::Output {
+      return Err(GpioError::InvalidFunction(self.num));
+    }
+    write_level(self.num, level);
+    Ok(())
+  }
+}
+
+impl Drop for ConfiguredPin {
+  fn drop(&mut self) {
+    crate::release_dropped_pin(self.num);
+  }
+}
+
+/// Write the level of a specific pin
+fn write_level(num: u32, level: bool) {
+  let mask = 1 << (num & 31);
+  if level {
+    set_pins(pin_bank(num), mask);
+  } else {
+    clear_pins(pin_bank(num), mask);
+  }
+}
+
+impl Gpio {
+  /// Acquire a pin and configure its function and pull up/down setting at once. For an output pin
+  /// the ``initial`` level is written before the pin is switched to output, so it never drives a
+  /// different level.
+  /// Returns an Err if the pin is out of range, already in use or reserved, or an ``initial``
+  /// level is given for a function other than output. In this case the pin is not acquired.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let led = GPIO
+  ///     .with_mut(|gpio| gpio.get_pin_with_config(17, Function::Output, Pud::Disabled, Some(false)))
+  ///     .unwrap();
+  /// led.write(true).unwrap();
+  /// # }
+  /// ```
+  pub fn get_pin_with_config(
+    &mut self,
+    num: u32,
+    function: Function,
+    pud: Pud,
+    initial: Option<bool>,
+  ) -> Result<ConfiguredPin, GpioError> {
+    self.check_available(num)?;
+    if initial.is_some() && function != Function::Output {
+      return Err(GpioError::InvalidFunction(num));
+    }
+    self.used_pins[num as usize] = true;
+
+    let mask = 1 << (num & 31);
+    match pin_bank(num) {
+      GpioBank::Bank0 => pud_sequence(pud, mask, 0),
+      GpioBank::Bank1 => pud_sequence(pud, 0, mask),
+    }
+    if let Some(level) = initial {
+      write_level(num, level);
+    }
+    set_function(num, function);
+    crate::claim_pin(num);
+
+    Ok(ConfiguredPin { num, function, pud })
+  }
+}
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use super::*;
+  use crate::mock;
+
+  #[test]
+  fn unavailable_pin_is_rejected_before_the_configuration() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    let _used = gpio.get_pin(17).unwrap();
+    gpio.mark_reserved(18).unwrap();
+    let failures = [
+      (54, GpioError::OutOfRange(54)),
+      (17, GpioError::AlreadyInUse(17)),
+      (18, GpioError::Reserved(18)),
+    ];
+    for &(num, error) in failures.iter() {
+      // the initial level is invalid for an input as well, the pin itself is reported
+      let pin = gpio.get_pin_with_config(num, Function::Input, Pud::Disabled, Some(true));
+      assert_eq!(pin.err(), Some(error));
+    }
+    gpio.unmark_reserved(18);
+    assert!(mock::writes().is_empty());
+  }
+
+  #[test]
+  fn initial_level_is_rejected_for_an_input() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    let pin = gpio.get_pin_with_config(19, Function::Input, Pud::PullUp, Some(true));
+    assert_eq!(pin.err(), Some(GpioError::InvalidFunction(19)));
+    assert!(!gpio.is_pin_used(19));
+    assert!(mock::writes().is_empty());
+  }
+
+  #[test]
+  fn initial_level_is_written_before_the_pin_becomes_an_output() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    let led = gpio
+      .get_pin_with_config(17, Function::Output, Pud::Disabled, Some(true))
+      .ok()
+      .unwrap();
+    assert_eq!(led.function(), Function::Output);
+    assert_eq!(gpio.pin_function(17), Ok(Function::Output));
+    let writes = mock::writes();
+    let set = writes
+      .iter()
+      .position(|&write| write == (GPIO_BASE + 0x1C, 1 << 17));
+    let fsel = writes
+      .iter()
+      .rposition(|&(addr, _)| addr == GPIO_BASE + 0x04);
+    assert!(set.unwrap() < fsel.unwrap());
+    assert!(gpio.get_pin(17).is_err());
+  }
+}
@@ -0,0 +1,132 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Sample based debouncing
+//!
+//! Debounce a stream of raw pin level samples taken at a fixed cadence. This does not depend on any time source, a
+//! level is only considered stable once the given number of consecutive samples agree on it.
+//!
+
+/// Debounce raw level samples. A new level is reported once it has been sampled the given number of times in a row.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn sample() -> bool { true }
+/// # fn doc() {
+/// let mut debouncer = Debouncer::new(5);
+/// loop {
+///     // sample the raw level of the pin at a fixed cadence
+///     if let Some(level) = debouncer.push(sample()) {
+///         println!("level changed to {}", level);
+///     }
+/// }
+/// # }
+/// ```
+pub struct Debouncer {
+  count: u32,
+  level: Option<bool>,
+  candidate: bool,
+  seen: u32,
+}
+
+impl Debouncer {
+  /// Create a new debouncer requiring ``count`` consistent samples before a level is reported. The initial level is
+  /// unknown, so the first stable level is always reported.
+  pub const fn new(count: u32) -> Self {
+    Self {
+      count,
+      level: None,
+      candidate: false,
+      seen: 0,
+    }
+  }
+
+  /// Feed the next raw sample. Returns the new debounced level if it has changed with this sample.
+  pub fn push(&mut self, sample: bool) -> Option<bool> {
+    if sample != self.candidate {
+      self.candidate = sample;
+      self.seen = 0;
+    }
+    self.seen = self.seen.saturating_add(1);
+    if self.seen >= self.count && self.level != Some(sample) {
+      self.level = Some(sample);
+      return self.level;
+    }
+    None
+  }
+
+  /// The current debounced level, ``None`` until the first stable level has been sampled
+  pub fn level(&self) -> Option<bool> {
+    self.level
+  }
+}
+
+/// Debounce a stream of raw level samples. The returned iterator yields the debounced level each time it changes.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// let samples = [false, true, false, true, true, true, false, true];
+/// let levels: Vec<bool> = debounce(samples.iter().copied(), 3).collect();
+/// assert_eq!(levels, [true]);
+/// # }
+/// ```
+pub fn debounce<I: Iterator<Item = bool>>(samples: I, count: u32) -> impl Iterator<Item = bool> {
+  samples
+    .scan(Debouncer::new(count), |debouncer, sample| {
+      Some(debouncer.push(sample))
+    })
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reports_first_stable_level() {
+    let mut debouncer = Debouncer::new(3);
+    assert_eq!(debouncer.push(false), None);
+    assert_eq!(debouncer.push(false), None);
+    assert_eq!(debouncer.level(), None);
+    assert_eq!(debouncer.push(false), Some(false));
+    assert_eq!(debouncer.level(), Some(false));
+  }
+
+  #[test]
+  fn reports_a_level_only_once() {
+    let mut debouncer = Debouncer::new(2);
+    assert_eq!(debouncer.push(true), None);
+    assert_eq!(debouncer.push(true), Some(true));
+    assert_eq!(debouncer.push(true), None);
+    assert_eq!(debouncer.push(true), None);
+  }
+
+  #[test]
+  fn bounce_restarts_the_count() {
+    let mut debouncer = Debouncer::new(3);
+    for _ in 0..3 {
+      debouncer.push(false);
+    }
+    assert_eq!(debouncer.push(true), None);
+    assert_eq!(debouncer.push(true), None);
+    // a single bounce back to low restarts the count of the high samples
+    assert_eq!(debouncer.push(false), None);
+    assert_eq!(debouncer.push(true), None);
+    assert_eq!(debouncer.push(true), None);
+    assert_eq!(debouncer.push(true), Some(true));
+    assert_eq!(debouncer.level(), Some(true));
+  }
+
+  #[test]
+  fn debounce_yields_level_changes() {
+    let samples = [
+      false, false, true, false, true, true, true, true, false, false, false,
+    ];
+    let levels: Vec<bool> = debounce(samples.iter().copied(), 3).collect();
+    assert_eq!(levels, [true, false]);
+  }
+}
@@ -14,7 +14,10 @@
 //! access for the GPIO's accepting the "danger" and the fact it is <b>unsafe</b> to do so
 //!
 
+use crate::interface::GPIO_BASE;
+#[cfg(not(feature = "mock-mmio"))]
 use core::ptr::{read_volatile, write_volatile};
+
 /// Let a LED lit connected to the given GPIO number
 ///
 /// # Safety
@@ -23,13 +26,88 @@ use core::ptr::{read_volatile, write_volatile};
 pub unsafe fn lit_debug_led(num: u32) {
   let fsel_num = num / 10;
   let fsel_shift = (num % 10) * 3;
-  let fsel_addr = 0x3f20_0000 + 4 * fsel_num;
-  let set_addr = 0x3f20_001c + num / 32;
-  let mut fsel: u32 = read_volatile(fsel_addr as *const u32);
+  let fsel_addr = GPIO_BASE + 4 * fsel_num as usize;
+  let set_addr = GPIO_BASE + 0x1c + 4 * (num / 32) as usize;
+  let mut fsel: u32 = read_register(fsel_addr);
   fsel &= !(7 << fsel_shift);
   fsel |= 1 << fsel_shift;
-  write_volatile(fsel_addr as *mut u32, fsel);
+  write_register(fsel_addr, fsel);
 
   let set: u32 = 1 << (num & 0x1F);
-  write_volatile(set_addr as *mut u32, set);
+  write_register(set_addr, set);
+}
+
+/// Let a LED connected to the given GPIO number go dark again after it has been lit with
+/// [lit_debug_led]. Together both functions allow simple blink patterns, e.g. to signal a number
+/// of steps during early boot:
+/// ```no_run
+/// # use ruspiro_gpio::debug::*;
+/// # fn delay() {}
+/// # fn doc() {
+/// for _ in 0..3 {
+///     unsafe { lit_debug_led(17) };
+///     delay();
+///     unsafe { clear_debug_led(17) };
+///     delay();
+/// }
+/// # }
+/// ```
+///
+/// # Safety
+/// This access is unsafe as it circumvent all safe constructs available in the `ruspiro-gpio`crate.
+#[no_mangle]
+pub unsafe fn clear_debug_led(num: u32) {
+  let clear_addr = GPIO_BASE + 0x28 + 4 * (num / 32) as usize;
+  let clear: u32 = 1 << (num & 0x1F);
+  write_register(clear_addr, clear);
+}
+
+#[cfg(not(feature = "mock-mmio"))]
+unsafe fn read_register(addr: usize) -> u32 {
+  read_volatile(addr as *const u32)
+}
+
+#[cfg(not(feature = "mock-mmio"))]
+unsafe fn write_register(addr: usize, value: u32) {
+  write_volatile(addr as *mut u32, value)
+}
+
+// the debug LED uses the mocked registers as well, there is no GPIO behind the addresses on the host
+#[cfg(feature = "mock-mmio")]
+unsafe fn read_register(addr: usize) -> u32 {
+  crate::mock::read(addr)
+}
+
+#[cfg(feature = "mock-mmio")]
+unsafe fn write_register(addr: usize, value: u32) {
+  crate::mock::write(addr, value)
+}
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use super::*;
+  use crate::mock;
+
+  #[test]
+  fn debug_led_addresses_the_register_and_bit_of_the_pin() {
+    // GPIO number, function select register, shift in the register, set/clear offset, bit
+    let pins = [
+      (16, 0x04, 18, 0x00, 1 << 16),
+      (35, 0x0C, 15, 0x04, 1 << 3),
+      (47, 0x10, 21, 0x04, 1 << 15),
+    ];
+    for &(num, fsel, shift, bank, bit) in pins.iter() {
+      let _test = crate::exclusive_test();
+      let fsel_addr = GPIO_BASE + fsel;
+      // the function of the other pins sharing the register is kept
+      mock::poke(fsel_addr, !0);
+      unsafe { lit_debug_led(num) };
+      assert_eq!(mock::peek(fsel_addr), !(0b110 << shift), "GPIO {}", num);
+      let set = mock::writes_to(GPIO_BASE + 0x1C + bank);
+      assert_eq!(set, [bit], "GPIO {}", num);
+      unsafe { clear_debug_led(num) };
+      let clear = mock::writes_to(GPIO_BASE + 0x28 + bank);
+      assert_eq!(clear, [bit], "GPIO {}", num);
+    }
+  }
 }
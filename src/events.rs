@@ -0,0 +1,191 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # GPIO event combinations
+//!
+//! A set of GPIO detect events that shall be armed together for the same pin and are routed to
+//! the same event handler.
+//!
+
+use crate::GpioEvent;
+use core::ops::{BitOr, BitOrAssign};
+
+/// A combination of GPIO detect events. Each event is detected independently and all of them are
+/// routed to the same event handler.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// GPIO.with_mut(|gpio| {
+///     let pin = gpio.get_pin(12).unwrap().into_input();
+///     gpio.register_recurring_event_handler(
+///         &pin,
+///         GpioEvents::RISING | GpioEvents::HIGH,
+///         || println!("GPIO Event raised")
+///     ).unwrap();
+/// });
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpioEvents(u8);
+
+impl GpioEvents {
+  /// Level changes from low to high
+  pub const RISING: GpioEvents = GpioEvents(1 << 0);
+  /// Level changes from high to low
+  pub const FALLING: GpioEvents = GpioEvents(1 << 1);
+  /// Level is high
+  pub const HIGH: GpioEvents = GpioEvents(1 << 2);
+  /// Level is low
+  pub const LOW: GpioEvents = GpioEvents(1 << 3);
+  /// Level changes from low to high, not bound to the GPIO clock rate
+  pub const ASYNC_RISING: GpioEvents = GpioEvents(1 << 4);
+  /// Level changes from high to low, not bound to the GPIO clock rate
+  pub const ASYNC_FALLING: GpioEvents = GpioEvents(1 << 5);
+
+  /// The empty event set
+  pub const fn empty() -> Self {
+    GpioEvents(0)
+  }
+
+  /// Create the event set from the raw bits
+  pub(crate) const fn from_bits(bits: u8) -> Self {
+    GpioEvents(bits & 0x3F)
+  }
+
+  /// Returns true if no event is contained in the set
+  pub const fn is_empty(&self) -> bool {
+    self.0 == 0
+  }
+
+  /// Returns true if all events of ``other`` are contained in this set
+  pub const fn contains(&self, other: GpioEvents) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  /// Iterate over the single [GpioEvent]s contained in this set
+  pub fn iter(&self) -> impl Iterator<Item = GpioEvent> {
+    let bits = self.0;
+    (0..6)
+      .filter(move |bit| bits & (1 << bit) != 0)
+      .map(|bit| match bit {
+        0 => GpioEvent::RisingEdge,
+        1 => GpioEvent::FallingEdge,
+        2 => GpioEvent::High,
+        3 => GpioEvent::Low,
+        4 => GpioEvent::AsyncRisingEdge,
+        _ => GpioEvent::AsyncFallingEdge,
+      })
+  }
+}
+
+impl BitOr for GpioEvents {
+  type Output = Self;
+
+  fn bitor(self, rhs: Self) -> Self {
+    GpioEvents(self.0 | rhs.0)
+  }
+}
+
+impl BitOrAssign for GpioEvents {
+  fn bitor_assign(&mut self, rhs: Self) {
+    self.0 |= rhs.0;
+  }
+}
+
+impl From<GpioEvent> for GpioEvents {
+  fn from(event: GpioEvent) -> Self {
+    match event {
+      GpioEvent::RisingEdge => GpioEvents::RISING,
+      GpioEvent::FallingEdge => GpioEvents::FALLING,
+      GpioEvent::BothEdges => GpioEvents::RISING | GpioEvents::FALLING,
+      GpioEvent::High => GpioEvents::HIGH,
+      GpioEvent::Low => GpioEvents::LOW,
+      GpioEvent::AsyncRisingEdge => GpioEvents::ASYNC_RISING,
+      GpioEvent::AsyncFallingEdge => GpioEvents::ASYNC_FALLING,
+      GpioEvent::AsyncBothEdges => GpioEvents::ASYNC_RISING | GpioEvents::ASYNC_FALLING,
+    }
+  }
+}
+
+impl From<Option<GpioEvent>> for GpioEvents {
+  fn from(event: Option<GpioEvent>) -> Self {
+    event.map_or(GpioEvents::empty(), GpioEvents::from)
+  }
+}
+
+/// Token identifying the detect events armed for a pin by the registration of a recurring event
+/// handler. It is consumed by [Gpio::remove_event_handler_by_token](crate::Gpio::remove_event_handler_by_token)
+/// to deactivate exactly those events, leaving the events armed by others for the same pin active.
+#[derive(Debug)]
+pub struct EventHandlerToken {
+  pub(crate) pin: u32,
+  pub(crate) events: GpioEvents,
+}
+
+impl EventHandlerToken {
+  /// The number of the pin the event handler has been registered for
+  pub fn pin(&self) -> u32 {
+    self.pin
+  }
+
+  /// The detect events armed with the registration of the event handler
+  pub fn events(&self) -> GpioEvents {
+    self.events
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bits_follow_the_register_order() {
+    assert_eq!(GpioEvents::from_bits(0b00_0001), GpioEvents::RISING);
+    assert_eq!(GpioEvents::from_bits(0b00_0010), GpioEvents::FALLING);
+    assert_eq!(GpioEvents::from_bits(0b00_0100), GpioEvents::HIGH);
+    assert_eq!(GpioEvents::from_bits(0b00_1000), GpioEvents::LOW);
+    assert_eq!(GpioEvents::from_bits(0b01_0000), GpioEvents::ASYNC_RISING);
+    assert_eq!(GpioEvents::from_bits(0b10_0000), GpioEvents::ASYNC_FALLING);
+  }
+
+  #[test]
+  fn from_bits_drops_unknown_bits() {
+    assert_eq!(GpioEvents::from_bits(0xC0), GpioEvents::empty());
+    assert!(GpioEvents::from_bits(0xC0).is_empty());
+    assert_eq!(
+      GpioEvents::from_bits(0xFF),
+      GpioEvents::from(GpioEvent::BothEdges)
+        | GpioEvents::HIGH
+        | GpioEvents::LOW
+        | GpioEvents::from(GpioEvent::AsyncBothEdges)
+    );
+  }
+
+  #[test]
+  fn iter_yields_the_events_in_bit_order() {
+    let events = GpioEvents::ASYNC_FALLING | GpioEvents::HIGH | GpioEvents::RISING;
+    let expected = [
+      GpioEvent::RisingEdge,
+      GpioEvent::High,
+      GpioEvent::AsyncFallingEdge,
+    ];
+    assert!(events.iter().eq(expected.iter().copied()));
+    assert_eq!(GpioEvents::empty().iter().count(), 0);
+    assert_eq!(GpioEvents::from_bits(0x3F).iter().count(), 6);
+  }
+
+  #[test]
+  fn contains_checks_all_events() {
+    let mut events = GpioEvents::empty();
+    events |= GpioEvents::RISING;
+    events |= GpioEvent::Low.into();
+    assert!(events.contains(GpioEvents::RISING | GpioEvents::LOW));
+    assert!(!events.contains(GpioEvent::BothEdges.into()));
+    assert!(events.contains(GpioEvents::empty()));
+    assert_eq!(GpioEvents::from(None), GpioEvents::empty());
+  }
+}
@@ -0,0 +1,65 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # embedded-hal integration
+//!
+//! Implementation of the ``embedded-hal`` digital pin traits for the input and output pins. This allows the pins to
+//! be used with any device driver written against those traits.
+//!
+//! # Example
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! use embedded_hal::digital::v2::OutputPin;
+//!
+//! // a driver only knowing about the generic output pin
+//! fn lit<P: OutputPin>(led: &mut P) {
+//!     let _ = led.set_high();
+//! }
+//!
+//! # fn doc() {
+//! let mut led = GPIO.with_mut(|gpio| gpio.get_pin(17)).unwrap().into_output();
+//! lit(&mut led);
+//! # }
+//! ```
+//!
+
+use crate::{function, GpioError, Pin};
+use embedded_hal::digital::v2::{InputPin, OutputPin, ToggleableOutputPin};
+
+impl<PUD> OutputPin for Pin<function::Output, PUD> {
+  type Error = GpioError;
+
+  fn set_low(&mut self) -> Result<(), Self::Error> {
+    self.low();
+    Ok(())
+  }
+
+  fn set_high(&mut self) -> Result<(), Self::Error> {
+    self.high();
+    Ok(())
+  }
+}
+
+impl<PUD> ToggleableOutputPin for Pin<function::Output, PUD> {
+  type Error = GpioError;
+
+  fn toggle(&mut self) -> Result<(), Self::Error> {
+    Pin::toggle(self);
+    Ok(())
+  }
+}
+
+impl<PUD> InputPin for Pin<function::Input, PUD> {
+  type Error = GpioError;
+
+  fn is_high(&self) -> Result<bool, Self::Error> {
+    Ok(self.read_level())
+  }
+
+  fn is_low(&self) -> Result<bool, Self::Error> {
+    Ok(!self.read_level())
+  }
+}
@@ -0,0 +1,179 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Heartbeat status LED
+//!
+//! The [Heartbeat] blinks a status LED to indicate the system is alive. The code being monitored is expected to call
+//! [Heartbeat::keepalive] at least once per healthy blink period. If the keepalive is missing the LED switches to the
+//! fault blink pattern until the next keepalive arrives.
+//!
+//! Like the [AutoOff](crate::AutoOff) wrapper the time is injected by passing the current time to the functions. The
+//! unit of the time values is up to the caller, it only needs to be the same for all values passed.
+//!
+
+use crate::{function, Pin};
+
+/// A status LED blinking with the healthy period as long as keepalives arrive in time and with the fault period
+/// otherwise.
+pub struct Heartbeat<PUD> {
+  pin: Pin<function::Output, PUD>,
+  blink: Blink,
+}
+
+impl<PUD> Heartbeat<PUD> {
+  /// Take ownership of the output pin driving the status LED. The periods are the time between two toggles of the
+  /// LED. The deadline for the first keepalive starts with the first call to [Heartbeat::poll].
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn now() -> u64 { 0 }
+  /// # fn do_work() {}
+  /// # fn doc() {
+  /// let led = GPIO.with_mut(|gpio| gpio.get_pin(47).unwrap().into_output());
+  /// // blink slowly while healthy and fast once the main loop stalls
+  /// let mut heartbeat = Heartbeat::new(led, 500_000, 100_000);
+  /// loop {
+  ///     do_work();
+  ///     heartbeat.keepalive(now());
+  ///     heartbeat.poll(now());
+  /// }
+  /// # }
+  /// ```
+  pub fn new(pin: Pin<function::Output, PUD>, healthy_period: u64, fault_period: u64) -> Self {
+    pin.low();
+    Self {
+      pin,
+      blink: Blink::new(healthy_period, fault_period),
+    }
+  }
+
+  /// Signal the monitored code is alive. This extends the deadline to ``now + healthy_period`` and brings a faulted
+  /// heartbeat back to the healthy blink pattern.
+  pub fn keepalive(&mut self, now: u64) {
+    self.blink.keepalive(now);
+  }
+
+  /// Toggle the LED if the current blink period has passed. Returns whether the heartbeat is healthy.
+  pub fn poll(&mut self, now: u64) -> bool {
+    let (healthy, toggled) = self.blink.poll(now);
+    match toggled {
+      Some(true) => self.pin.high(),
+      Some(false) => self.pin.low(),
+      None => (),
+    }
+    healthy
+  }
+
+  /// Returns whether the keepalive deadline has passed at the given time
+  pub fn is_faulted(&self, now: u64) -> bool {
+    self.blink.is_faulted(now)
+  }
+
+  /// Release the status LED, it is left in its current state
+  pub fn into_inner(self) -> Pin<function::Output, PUD> {
+    self.pin
+  }
+}
+
+/// The blink pattern of a [Heartbeat] without the LED driven by it
+struct Blink {
+  healthy_period: u64,
+  fault_period: u64,
+  /// the time the keepalive is expected by, ``None`` until the first call to [Blink::poll]
+  deadline: Option<u64>,
+  /// the time the LED is toggled next
+  next_toggle: u64,
+  lit: bool,
+}
+
+impl Blink {
+  const fn new(healthy_period: u64, fault_period: u64) -> Self {
+    Self {
+      healthy_period,
+      fault_period,
+      deadline: None,
+      next_toggle: 0,
+      lit: false,
+    }
+  }
+
+  fn keepalive(&mut self, now: u64) {
+    if self.is_faulted(now) {
+      // restart the blink cycle so the healthy pattern is visible immediately
+      self.next_toggle = now;
+    }
+    self.deadline = Some(now.saturating_add(self.healthy_period));
+  }
+
+  /// Returns whether the heartbeat is healthy and the new state of the LED if it is toggled with this poll
+  fn poll(&mut self, now: u64) -> (bool, Option<bool>) {
+    let deadline = *self
+      .deadline
+      .get_or_insert_with(|| now.saturating_add(self.healthy_period));
+    let healthy = now < deadline;
+    if now < self.next_toggle {
+      return (healthy, None);
+    }
+    self.lit = !self.lit;
+    let period = if healthy {
+      self.healthy_period
+    } else {
+      self.fault_period
+    };
+    self.next_toggle = now.saturating_add(period);
+    (healthy, Some(self.lit))
+  }
+
+  fn is_faulted(&self, now: u64) -> bool {
+    self.deadline.map_or(false, |deadline| now >= deadline)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn blinks_with_the_healthy_period() {
+    let mut blink = Blink::new(100, 10);
+    assert_eq!(blink.poll(0), (true, Some(true)));
+    assert_eq!(blink.poll(50), (true, None));
+    blink.keepalive(90);
+    assert_eq!(blink.poll(100), (true, Some(false)));
+    assert_eq!(blink.poll(150), (true, None));
+  }
+
+  #[test]
+  fn first_deadline_starts_with_the_first_poll() {
+    let mut blink = Blink::new(100, 10);
+    assert!(!blink.is_faulted(1_000));
+    blink.poll(1_000);
+    assert!(!blink.is_faulted(1_099));
+    assert!(blink.is_faulted(1_100));
+  }
+
+  #[test]
+  fn missing_keepalive_switches_to_the_fault_period() {
+    let mut blink = Blink::new(100, 10);
+    blink.poll(0);
+    assert_eq!(blink.poll(100), (false, Some(false)));
+    assert_eq!(blink.poll(105), (false, None));
+    assert_eq!(blink.poll(110), (false, Some(true)));
+  }
+
+  #[test]
+  fn keepalive_recovers_immediately() {
+    let mut blink = Blink::new(100, 10);
+    blink.poll(0);
+    blink.poll(100);
+    blink.keepalive(103);
+    assert!(!blink.is_faulted(103));
+    // the blink cycle restarts with the keepalive instead of waiting for the pending fault toggle
+    assert_eq!(blink.poll(103), (true, Some(true)));
+    assert_eq!(blink.poll(200), (true, None));
+    assert_eq!(blink.poll(203), (false, Some(false)));
+  }
+}
@@ -8,43 +8,167 @@
 //! # Gpio low level functions
 //!
 
-use crate::GpioEvent;
+#[cfg(feature = "mock-mmio")]
+use crate::mock::registers::*;
+use crate::{GpioEvent, GpioEvents};
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+#[cfg(not(feature = "mock-mmio"))]
 use ruspiro_mmio_register::*;
 
+#[cfg(all(feature = "ruspiro_pi3", feature = "ruspiro_pi4"))]
+compile_error!(
+  "the features ruspiro_pi3 and ruspiro_pi4 are mutually exclusive, choose only one of them"
+);
+
 // MMIO peripheral base address based on the pi model we build for
 #[cfg(feature = "ruspiro_pi3")]
-const PERIPHERAL_BASE: usize = 0x3F00_0000;
+pub(crate) const PERIPHERAL_BASE: usize = 0x3F00_0000;
+#[cfg(feature = "ruspiro_pi4")]
+pub(crate) const PERIPHERAL_BASE: usize = 0xFE00_0000;
 
 /// Base address for GPIO MMIO registers
-const GPIO_BASE: usize = PERIPHERAL_BASE + 0x0020_0000;
+pub(crate) const GPIO_BASE: usize = PERIPHERAL_BASE + 0x0020_0000;
+
+/// Base address for the GPIO pad control MMIO registers
+pub(crate) const PADS_BASE: usize = PERIPHERAL_BASE + 0x0010_0000;
+
+/// Base address for the system timer MMIO registers
+#[cfg(any(
+  feature = "soft-pwm",
+  feature = "event-coalescing",
+  feature = "mock-mmio"
+))]
+pub(crate) const SYS_TIMER_BASE: usize = PERIPHERAL_BASE + 0x3000;
+
+/// The number of GPIO pins available
+pub(crate) const GPIO_PIN_COUNT: u32 = 54;
+
+/// The default number of cycles to wait for the control signal to settle within the pull up/down
+/// update cycle. The BCM2835 datasheet requires to wait 150 cycles after setting the control value
+/// and after clocking it into the pins.
+pub(crate) const PUD_SETTLE_CYCLES: u32 = 150;
+
+/// The number of cycles to wait within the pull up/down update cycle as configured by
+/// [Gpio::set_pud_settle_cycles](crate::Gpio::set_pud_settle_cycles)
+pub(crate) static PUD_SETTLE: AtomicU32 = AtomicU32::new(PUD_SETTLE_CYCLES);
+
+/// Shadow of the levels last written to the pins of both banks. Bit ``n`` is the level of GPIO ``n``.
+static DRIVEN_LEVELS: AtomicU64 = AtomicU64::new(0);
 
 /// The two existing GPIO banks
-pub(crate) enum GpioBank {
+#[derive(Clone, Copy)]
+pub enum GpioBank {
+  /// GPIO bank 0 containing the pins 0..31
   Bank0,
+  /// GPIO bank 1 containing the pins 32..53
   Bank1,
 }
 
-// GPIO pin function register config values
+impl GpioBank {
+  /// The number of pins of this bank
+  pub const fn pin_count(self) -> u32 {
+    match self {
+      GpioBank::Bank0 => 32,
+      GpioBank::Bank1 => GPIO_PIN_COUNT - 32,
+    }
+  }
+}
+
+/// Get the GPIO bank a specific gpio pin belongs to
+pub(crate) fn pin_bank(pin: u32) -> GpioBank {
+  if pin < 32 {
+    GpioBank::Bank0
+  } else {
+    GpioBank::Bank1
+  }
+}
+
+/// The GPIO pin functions with their function select register config values
 #[repr(u32)]
-pub(crate) enum Function {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+  /// The pin is an input
   Input = 0b000,
+  /// The pin is an output
   Output = 0b001,
+  /// The pin takes alternate function 0
   Alt0 = 0b100,
+  /// The pin takes alternate function 1
   Alt1 = 0b101,
+  /// The pin takes alternate function 2
   Alt2 = 0b110,
+  /// The pin takes alternate function 3
   Alt3 = 0b111,
+  /// The pin takes alternate function 4
   Alt4 = 0b011,
+  /// The pin takes alternate function 5
   Alt5 = 0b010,
 }
 
-// GPIO pull up/down register config values
+impl Function {
+  /// Decode the function from the lower 3 bits of the given function select register value
+  pub(crate) fn from_bits(bits: u32) -> Self {
+    match bits & 0x7 {
+      0b000 => Function::Input,
+      0b001 => Function::Output,
+      0b100 => Function::Alt0,
+      0b101 => Function::Alt1,
+      0b110 => Function::Alt2,
+      0b111 => Function::Alt3,
+      0b011 => Function::Alt4,
+      _ => Function::Alt5,
+    }
+  }
+}
+
+/// The alternative functions a GPIO pin could take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AltFunction {
+  /// Alternative function 0
+  Alt0,
+  /// Alternative function 1
+  Alt1,
+  /// Alternative function 2
+  Alt2,
+  /// Alternative function 3
+  Alt3,
+  /// Alternative function 4
+  Alt4,
+  /// Alternative function 5
+  Alt5,
+}
+
+impl From<AltFunction> for Function {
+  fn from(alt: AltFunction) -> Self {
+    match alt {
+      AltFunction::Alt0 => Function::Alt0,
+      AltFunction::Alt1 => Function::Alt1,
+      AltFunction::Alt2 => Function::Alt2,
+      AltFunction::Alt3 => Function::Alt3,
+      AltFunction::Alt4 => Function::Alt4,
+      AltFunction::Alt5 => Function::Alt5,
+    }
+  }
+}
+
+/// The GPIO pull up/down settings with their register config values
 #[repr(u8)]
-pub(crate) enum Pud {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pud {
+  /// PullUp/Down disabled
   Disabled = 0b00,
+  /// PullDown enabled
   PullDown = 0b01,
+  /// PullUp enabled
   PullUp = 0b10,
 }
 
+// Verify the GPPUD control values at compile time against the BCM2835 datasheet. A wrong value
+// will fail the build with a mismatched array length
+const _: [(); 0b00] = [(); Pud::Disabled as usize];
+const _: [(); 0b01] = [(); Pud::PullDown as usize];
+const _: [(); 0b10] = [(); Pud::PullUp as usize];
+
 /// Activate the event detection for a specific gpio pin
 pub(crate) fn activate_detect_event(pin: u32, event: GpioEvent) {
   let slot = pin & 31;
@@ -91,7 +215,6 @@ pub(crate) fn activate_detect_event(pin: u32, event: GpioEvent) {
 }
 
 /// De-activate the event detection for a specific gpio pin
-#[allow(dead_code)]
 pub(crate) fn deactivate_detect_event(pin: u32, event: GpioEvent) {
   let slot = pin & 31;
   let event_field = RegisterField::<u32>::new(1, slot);
@@ -136,29 +259,144 @@ pub(crate) fn deactivate_detect_event(pin: u32, event: GpioEvent) {
   }
 }
 
+/// Activate the given detect events for a specific gpio pin in addition to the events already
+/// active for this pin
+pub(crate) fn activate_detect_events(pin: u32, events: GpioEvents) {
+  for event in events.iter() {
+    activate_detect_event(pin, event);
+  }
+}
+
+/// Arm exactly the given detect events for a specific gpio pin. Any other event detection of this
+/// pin is de-activated, so an empty set disables the detection for this pin.
+pub(crate) fn set_detect_events(pin: u32, events: GpioEvents) {
+  deactivate_all_detect_events(pin);
+  for event in events.iter() {
+    activate_detect_event(pin, event);
+  }
+}
+
 /// De-activate all events detection for a specific gpio pin
 pub(crate) fn deactivate_all_detect_events(pin: u32) {
-  let slot = pin & 31;
-  let event_field = RegisterField::<u32>::new(1, slot);
-  match pin / 32 {
+  for event in GpioEvent::all() {
+    deactivate_detect_event(pin, event);
+  }
+}
+
+/// Get the location of the function select field of a specific gpio pin. Returns the index of the
+/// ``GPFSEL`` register (0..5) and the bit shift of the 3 bit wide field within this register. Each
+/// function select register covers 10 consecutive pins.
+/// # Example
+/// ```
+/// # use ruspiro_gpio::*;
+/// assert_eq!(gpfsel_for(9), (0, 27));
+/// assert_eq!(gpfsel_for(10), (1, 0));
+/// assert_eq!(gpfsel_for(53), (5, 9));
+/// ```
+pub const fn gpfsel_for(pin: u32) -> (usize, u32) {
+  ((pin / 10) as usize, (pin % 10) * 3)
+}
+
+/// Switch the function of a specific gpio pin
+pub(crate) fn set_function(pin: u32, function: Function) {
+  let (reg, shift) = gpfsel_for(pin);
+  let field = RegisterField::<u32>::new(0x7, shift);
+  let value = function as u32;
+  match reg {
     0 => {
-      GPREN0::Register.modify(event_field, 0);
-      GPFEN0::Register.modify(event_field, 0);
-      GPHEN0::Register.modify(event_field, 0);
-      GPLEN0::Register.modify(event_field, 0);
-      GPAREN0::Register.modify(event_field, 0);
-      GPAFEN0::Register.modify(event_field, 0);
+      GPFSEL0::Register.modify(field, value);
     }
     1 => {
-      GPREN1::Register.modify(event_field, 0);
-      GPFEN1::Register.modify(event_field, 0);
-      GPHEN1::Register.modify(event_field, 0);
-      GPLEN1::Register.modify(event_field, 0);
-      GPAREN1::Register.modify(event_field, 0);
-      GPAFEN1::Register.modify(event_field, 0);
+      GPFSEL1::Register.modify(field, value);
+    }
+    2 => {
+      GPFSEL2::Register.modify(field, value);
+    }
+    3 => {
+      GPFSEL3::Register.modify(field, value);
+    }
+    4 => {
+      GPFSEL4::Register.modify(field, value);
+    }
+    5 => {
+      GPFSEL5::Register.modify(field, value);
     }
     _ => (),
   }
+  // ensure the function select is written before any further access to the GPIO
+  memory_barrier();
+}
+
+/// Read all function select registers
+pub(crate) fn get_function_selects() -> [u32; 6] {
+  [
+    GPFSEL0::Register.get(),
+    GPFSEL1::Register.get(),
+    GPFSEL2::Register.get(),
+    GPFSEL3::Register.get(),
+    GPFSEL4::Register.get(),
+    GPFSEL5::Register.get(),
+  ]
+}
+
+/// Update the function select registers. Only the bits given by the mask of each register are
+/// replaced by the corresponding bits of the value. Registers with an empty mask are not accessed.
+pub(crate) fn update_function_selects(masks: [u32; 6], values: [u32; 6]) {
+  let registers = [
+    GPFSEL0::Register,
+    GPFSEL1::Register,
+    GPFSEL2::Register,
+    GPFSEL3::Register,
+    GPFSEL4::Register,
+    GPFSEL5::Register,
+  ];
+  for ((register, mask), value) in registers.iter().zip(masks.iter()).zip(values.iter()) {
+    if *mask != 0 {
+      register.set((register.get() & !mask) | (value & mask));
+    }
+  }
+  // ensure the function selects are written before any further access to the GPIO
+  memory_barrier();
+}
+
+/// Read the current function of a specific gpio pin
+pub(crate) fn get_function(pin: u32) -> Function {
+  let (reg, shift) = gpfsel_for(pin);
+  let fsel = match reg {
+    0 => GPFSEL0::Register.get(),
+    1 => GPFSEL1::Register.get(),
+    2 => GPFSEL2::Register.get(),
+    3 => GPFSEL3::Register.get(),
+    4 => GPFSEL4::Register.get(),
+    _ => GPFSEL5::Register.get(),
+  };
+  Function::from_bits(fsel >> shift)
+}
+
+/// Drive all output pins of the specified bank given by the bit mask high with a single write
+pub(crate) fn set_pins(bank: GpioBank, mask: u32) {
+  track_levels(bank, mask, true);
+  match bank {
+    GpioBank::Bank0 => GPSET0::Register.set(mask),
+    GpioBank::Bank1 => GPSET1::Register.set(mask),
+  }
+}
+
+/// Drive all output pins of the specified bank given by the bit mask low with a single write
+pub(crate) fn clear_pins(bank: GpioBank, mask: u32) {
+  track_levels(bank, mask, false);
+  match bank {
+    GpioBank::Bank0 => GPCLR0::Register.set(mask),
+    GpioBank::Bank1 => GPCLR1::Register.set(mask),
+  }
+}
+
+/// Read the level of all pins of the specified bank
+pub(crate) fn get_levels(bank: GpioBank) -> u32 {
+  match bank {
+    GpioBank::Bank0 => GPLEV0::Register.get(),
+    GpioBank::Bank1 => GPLEV1::Register.get(),
+  }
 }
 
 /// Read the event detect status register for the specified bank
@@ -169,6 +407,38 @@ pub(crate) fn get_detected_events(bank: GpioBank) -> u32 {
   }
 }
 
+/// Iterator over the GPIO pin numbers that are flagged in a bit mask of a bank, e.g. the event
+/// detect status value
+pub(crate) struct BankPins {
+  events: u32,
+  base: u32,
+}
+
+impl BankPins {
+  pub(crate) fn new(events: u32, bank: GpioBank) -> Self {
+    let base = match bank {
+      GpioBank::Bank0 => 0,
+      GpioBank::Bank1 => 32,
+    };
+    Self { events, base }
+  }
+}
+
+impl Iterator for BankPins {
+  type Item = u32;
+
+  fn next(&mut self) -> Option<u32> {
+    if self.events == 0 {
+      None
+    } else {
+      let bit = self.events.trailing_zeros();
+      // clear the lowest bit set as this one is handled now
+      self.events &= self.events - 1;
+      Some(self.base + bit)
+    }
+  }
+}
+
 /// Reset the event detect status register for the specified bank to acknowledge the
 /// event within the interrupt handler
 pub(crate) fn acknowledge_detected_events(events: u32, bank: GpioBank) {
@@ -178,6 +448,202 @@ pub(crate) fn acknowledge_detected_events(events: u32, bank: GpioBank) {
   }
 }
 
+/// Read the event detect enable registers of the specified bank. The values are returned in the
+/// order rising edge, falling edge, high level, low level, async rising edge, async falling edge
+pub(crate) fn get_detect_enables(bank: GpioBank) -> [u32; 6] {
+  match bank {
+    GpioBank::Bank0 => [
+      GPREN0::Register.get(),
+      GPFEN0::Register.get(),
+      GPHEN0::Register.get(),
+      GPLEN0::Register.get(),
+      GPAREN0::Register.get(),
+      GPAFEN0::Register.get(),
+    ],
+    GpioBank::Bank1 => [
+      GPREN1::Register.get(),
+      GPFEN1::Register.get(),
+      GPHEN1::Register.get(),
+      GPLEN1::Register.get(),
+      GPAREN1::Register.get(),
+      GPAFEN1::Register.get(),
+    ],
+  }
+}
+
+/// Write the event detect enable registers of the specified bank. The values are expected in the
+/// same order as returned by [get_detect_enables]
+pub(crate) fn set_detect_enables(bank: GpioBank, enables: [u32; 6]) {
+  match bank {
+    GpioBank::Bank0 => {
+      GPREN0::Register.set(enables[0]);
+      GPFEN0::Register.set(enables[1]);
+      GPHEN0::Register.set(enables[2]);
+      GPLEN0::Register.set(enables[3]);
+      GPAREN0::Register.set(enables[4]);
+      GPAFEN0::Register.set(enables[5]);
+    }
+    GpioBank::Bank1 => {
+      GPREN1::Register.set(enables[0]);
+      GPFEN1::Register.set(enables[1]);
+      GPHEN1::Register.set(enables[2]);
+      GPLEN1::Register.set(enables[3]);
+      GPAREN1::Register.set(enables[4]);
+      GPAFEN1::Register.set(enables[5]);
+    }
+  }
+}
+
+/// Apply the pull up/down setting to all pins given by the bit masks of both banks within a single
+/// pud change cycle
+#[cfg(not(feature = "ruspiro_pi4"))]
+pub(crate) fn pud_sequence(pud: Pud, bank0_mask: u32, bank1_mask: u32) {
+  let settle_cycles = PUD_SETTLE.load(Ordering::Acquire);
+  // finish any outstanding access to other peripherals before the sequence starts
+  memory_barrier();
+  // 1. write the desired pud control value to the PUD control register
+  GPPUD::Register.modify(GPPUD::PUD, pud as u32);
+  // 2. wait for the control signal to settle (150 cycles by default)
+  wait_cycles(settle_cycles);
+  // 3. write the pins to upate into the PUDCLCK registers
+  GPPUDCLK0::Register.set(bank0_mask);
+  GPPUDCLK1::Register.set(bank1_mask);
+  // 4. wait for the new settings to settle (150 cycles by default)
+  wait_cycles(settle_cycles);
+  // 5. clear the pud control value in the PUD control register
+  GPPUD::Register.set(0x0);
+  // 6. remove the clock from the PUDCLCK registers to finish the update cycle
+  GPPUDCLK0::Register.set(0x0);
+  GPPUDCLK1::Register.set(0x0);
+  // ensure the sequence has completed before any other peripheral is accessed
+  memory_barrier();
+}
+
+/// Apply the pull up/down setting to the pins given by the bit masks of both banks. The BCM2711
+/// does not require the clocked update cycle, each pin has its own 2 bit field in the pull up/down
+/// control registers that is written directly.
+#[cfg(feature = "ruspiro_pi4")]
+pub(crate) fn pud_sequence(pud: Pud, bank0_mask: u32, bank1_mask: u32) {
+  // the BCM2711 encodes pull up and pull down the other way round than the BCM2835
+  let value = match pud {
+    Pud::Disabled => 0b00,
+    Pud::PullUp => 0b01,
+    Pud::PullDown => 0b10,
+  };
+  memory_barrier();
+  let pins =
+    BankPins::new(bank0_mask, GpioBank::Bank0).chain(BankPins::new(bank1_mask, GpioBank::Bank1));
+  for pin in pins {
+    let field = RegisterField::<u32>::new(0b11, (pin % 16) * 2);
+    match pin / 16 {
+      0 => GPIO_PUP_PDN_CNTRL_REG0::Register.modify(field, value),
+      1 => GPIO_PUP_PDN_CNTRL_REG1::Register.modify(field, value),
+      2 => GPIO_PUP_PDN_CNTRL_REG2::Register.modify(field, value),
+      _ => GPIO_PUP_PDN_CNTRL_REG3::Register.modify(field, value),
+    };
+  }
+  memory_barrier();
+}
+
+/// Read back the pull up/down setting of a specific gpio pin. The BCM2835/BCM2837 do not provide a
+/// way to read back the setting, so this always returns ``None`` there.
+#[cfg(not(feature = "ruspiro_pi4"))]
+pub(crate) fn current_pud(_pin: u32) -> Option<Pud> {
+  None
+}
+
+/// Read back the pull up/down setting of a specific gpio pin from the BCM2711 pull up/down control
+/// registers. Returns ``None`` for the reserved field value.
+#[cfg(feature = "ruspiro_pi4")]
+pub(crate) fn current_pud(pin: u32) -> Option<Pud> {
+  let value = match pin / 16 {
+    0 => GPIO_PUP_PDN_CNTRL_REG0::Register.get(),
+    1 => GPIO_PUP_PDN_CNTRL_REG1::Register.get(),
+    2 => GPIO_PUP_PDN_CNTRL_REG2::Register.get(),
+    _ => GPIO_PUP_PDN_CNTRL_REG3::Register.get(),
+  };
+  match (value >> ((pin % 16) * 2)) & 0b11 {
+    0b00 => Some(Pud::Disabled),
+    0b01 => Some(Pud::PullUp),
+    0b10 => Some(Pud::PullDown),
+    _ => None,
+  }
+}
+
+/// Busy wait for the given number of cycles
+#[cfg(not(feature = "mock-mmio"))]
+pub(crate) fn wait_cycles(cycles: u32) {
+  for _ in 0..cycles {
+    // the asm! block is not marked as ``pure`` and therefore treated as having side effects, so
+    // the compiler will neither remove the NOP nor the surrounding loop
+    unsafe { asm!("NOP") }
+  }
+}
+
+/// Busy wait for the given number of cycles on the host running the mocked registers
+#[cfg(feature = "mock-mmio")]
+pub(crate) fn wait_cycles(cycles: u32) {
+  for _ in 0..cycles {
+    core::hint::spin_loop();
+  }
+}
+
+/// Data memory barrier. The BCM2835 peripherals do not guarantee the order of accesses to different
+/// peripherals, so a read from one peripheral might return data belonging to an access to another
+/// one. A barrier is required after a sequence of writes that needs to be completed before the
+/// next access, e.g. a function select change that needs to be in place before the level of the
+/// pin is written, or around the pull up/down sequence that might be interleaved with accesses to
+/// other peripherals.
+#[cfg(not(feature = "mock-mmio"))]
+pub(crate) fn memory_barrier() {
+  unsafe { asm!("dmb sy") }
+}
+
+/// Memory barrier on the host running the mocked registers, they are plain memory
+#[cfg(feature = "mock-mmio")]
+pub(crate) fn memory_barrier() {
+  core::sync::atomic::fence(Ordering::SeqCst);
+}
+
+/// Record the level written to the pins given by the bit mask of the bank in the shadow of the
+/// driven levels
+pub(crate) fn track_levels(bank: GpioBank, mask: u32, level: bool) {
+  let mask = match bank {
+    GpioBank::Bank0 => mask as u64,
+    GpioBank::Bank1 => (mask as u64) << 32,
+  };
+  if level {
+    DRIVEN_LEVELS.fetch_or(mask, Ordering::AcqRel);
+  } else {
+    DRIVEN_LEVELS.fetch_and(!mask, Ordering::AcqRel);
+  }
+}
+
+/// Get the level last written to a specific pin from the shadow of the driven levels
+pub(crate) fn tracked_level(pin: u32) -> bool {
+  DRIVEN_LEVELS.load(Ordering::Acquire) & (1 << pin) != 0
+}
+
+/// Run the closure with the IRQ's masked on the current core. The previous mask state is restored
+/// afterwards, so this could be nested.
+#[cfg(not(feature = "mock-mmio"))]
+pub(crate) fn without_interrupts<R, F: FnOnce() -> R>(f: F) -> R {
+  let daif: u64;
+  unsafe {
+    asm!("mrs {}, daif", out(reg) daif);
+    asm!("msr daifset, #2");
+  }
+  let result = f();
+  unsafe { asm!("msr daif, {}", in(reg) daif) };
+  result
+}
+
+/// Run the closure on the host running the mocked registers. There are no IRQ's to mask there.
+#[cfg(feature = "mock-mmio")]
+pub(crate) fn without_interrupts<R, F: FnOnce() -> R>(f: F) -> R {
+  f()
+}
+
 // Define the registers of the GPIO that are used to access the pin's
 define_mmio_register! [
     /// Alt-Function select register for pin 0..9
@@ -204,14 +670,6 @@ define_mmio_register! [
     pub(crate) GPLEV0<ReadOnly<u32>@(GPIO_BASE + 0x34)>,
     /// Read Pin level register for pin 32..53
     pub(crate) GPLEV1<ReadOnly<u32>@(GPIO_BASE + 0x38)>,
-    /// Pull-Up/Down configuration register
-    pub(crate) GPPUD<ReadWrite<u32>@(GPIO_BASE + 0x94)> {
-        PUD OFFSET(0) BITS(2)
-    },
-    /// Pull-Up/Down clock register for pin 0..31
-    pub(crate) GPPUDCLK0<ReadWrite<u32>@(GPIO_BASE + 0x98)>,
-    /// Pull-Up/Down clock register for pin 32..53
-    pub(crate) GPPUDCLK1<ReadWrite<u32>@(GPIO_BASE + 0x9C)>,
     /// GPIO Pin event detect status bank 0 (pin 0..31)
     GPEDS0<ReadWrite<u32>@(GPIO_BASE + 0x40)>,
     /// GPIO Pin event detect status bank 1 (pin 32..53)
@@ -241,3 +699,192 @@ define_mmio_register! [
     /// GPIO Pin async falling edge detect enable bank 1 (pin 32..53)
     GPAFEN1<ReadWrite<u32>@(GPIO_BASE + 0x8c)>
 ];
+
+// Define the pull up/down control registers of the BCM2835/BCM2837, the BCM2711 no longer provides them
+#[cfg(not(feature = "ruspiro_pi4"))]
+define_mmio_register! [
+    /// Pull-Up/Down configuration register
+    GPPUD<ReadWrite<u32>@(GPIO_BASE + 0x94)> {
+        PUD OFFSET(0) BITS(2)
+    },
+    /// Pull-Up/Down clock register for pin 0..31
+    GPPUDCLK0<ReadWrite<u32>@(GPIO_BASE + 0x98)>,
+    /// Pull-Up/Down clock register for pin 32..53
+    GPPUDCLK1<ReadWrite<u32>@(GPIO_BASE + 0x9C)>
+];
+
+// Define the pull up/down control registers only available on the BCM2711
+#[cfg(feature = "ruspiro_pi4")]
+define_mmio_register! [
+    /// Pull-Up/Down control register for pin 0..15
+    GPIO_PUP_PDN_CNTRL_REG0<ReadWrite<u32>@(GPIO_BASE + 0xE4)>,
+    /// Pull-Up/Down control register for pin 16..31
+    GPIO_PUP_PDN_CNTRL_REG1<ReadWrite<u32>@(GPIO_BASE + 0xE8)>,
+    /// Pull-Up/Down control register for pin 32..47
+    GPIO_PUP_PDN_CNTRL_REG2<ReadWrite<u32>@(GPIO_BASE + 0xEC)>,
+    /// Pull-Up/Down control register for pin 48..57
+    GPIO_PUP_PDN_CNTRL_REG3<ReadWrite<u32>@(GPIO_BASE + 0xF0)>
+];
+
+// Define the system timer registers used to drive the software PWM and to end the event coalescing window
+#[cfg(any(feature = "soft-pwm", feature = "event-coalescing"))]
+define_mmio_register! [
+    /// System timer control/status register
+    pub(crate) SYS_TIMER_CS<ReadWrite<u32>@(SYS_TIMER_BASE)>,
+    /// System timer counter lower 32 bits
+    pub(crate) SYS_TIMER_CLO<ReadOnly<u32>@(SYS_TIMER_BASE + 0x04)>
+];
+
+// Define the system timer compare register used to end the event coalescing window
+#[cfg(feature = "event-coalescing")]
+define_mmio_register! [
+    /// System timer compare register 1
+    pub(crate) SYS_TIMER_C1<ReadWrite<u32>@(SYS_TIMER_BASE + 0x10)>
+];
+
+// Define the system timer compare register used to drive the software PWM
+#[cfg(feature = "soft-pwm")]
+define_mmio_register! [
+    /// System timer compare register 3
+    pub(crate) SYS_TIMER_C3<ReadWrite<u32>@(SYS_TIMER_BASE + 0x18)>
+];
+
+// Define the pad control registers of the GPIO pin groups
+define_mmio_register! [
+    /// Pad control register for pin 0..27
+    pub(crate) PADS_GPIO_0_27<ReadWrite<u32>@(PADS_BASE + 0x2C)>,
+    /// Pad control register for pin 28..45
+    pub(crate) PADS_GPIO_28_45<ReadWrite<u32>@(PADS_BASE + 0x30)>,
+    /// Pad control register for pin 46..53
+    pub(crate) PADS_GPIO_46_53<ReadWrite<u32>@(PADS_BASE + 0x34)>
+];
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use super::*;
+  use crate::mock;
+
+  #[test]
+  fn detect_event_sets_the_pin_bit_of_its_bank() {
+    let _test = crate::exclusive_test();
+    activate_detect_event(17, GpioEvent::RisingEdge);
+    activate_detect_event(40, GpioEvent::FallingEdge);
+    assert_eq!(mock::peek(GPIO_BASE + 0x4C), 1 << 17);
+    assert_eq!(mock::peek(GPIO_BASE + 0x5C), 1 << 8);
+    assert_eq!(
+      get_detect_enables(GpioBank::Bank0),
+      [1 << 17, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(get_detect_enables(GpioBank::Bank1), [0, 1 << 8, 0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn both_edges_arm_the_rising_and_falling_detection() {
+    let _test = crate::exclusive_test();
+    activate_detect_event(5, GpioEvent::BothEdges);
+    activate_detect_event(53, GpioEvent::AsyncBothEdges);
+    assert_eq!(
+      get_detect_enables(GpioBank::Bank0),
+      [1 << 5, 1 << 5, 0, 0, 0, 0]
+    );
+    assert_eq!(
+      get_detect_enables(GpioBank::Bank1),
+      [0, 0, 0, 0, 1 << 21, 1 << 21]
+    );
+  }
+
+  #[test]
+  fn deactivate_keeps_the_other_pins_armed() {
+    let _test = crate::exclusive_test();
+    activate_detect_event(3, GpioEvent::High);
+    activate_detect_event(4, GpioEvent::High);
+    deactivate_detect_event(3, GpioEvent::High);
+    assert_eq!(mock::peek(GPIO_BASE + 0x64), 1 << 4);
+  }
+
+  #[test]
+  fn function_select_only_changes_the_field_of_the_pin() {
+    let _test = crate::exclusive_test();
+    set_function(10, Function::Output);
+    set_function(19, Function::Alt5);
+    set_function(53, Function::Alt0);
+    assert_eq!(mock::peek(GPIO_BASE + 0x04), 0b001 | (0b010 << 27));
+    assert_eq!(mock::peek(GPIO_BASE + 0x14), 0b100 << 9);
+    assert_eq!(get_function(19), Function::Alt5);
+    assert_eq!(get_function(11), Function::Input);
+  }
+
+  /// The register writes of a full pull up/down cycle on the BCM2835/BCM2837
+  #[cfg(not(feature = "ruspiro_pi4"))]
+  fn pud_cycle(control: u32, clk0: u32, clk1: u32) -> [(usize, u32); 6] {
+    let (gppud, gppudclk0, gppudclk1) = (GPIO_BASE + 0x94, GPIO_BASE + 0x98, GPIO_BASE + 0x9C);
+    [
+      (gppud, control),
+      (gppudclk0, clk0),
+      (gppudclk1, clk1),
+      (gppud, 0),
+      (gppudclk0, 0),
+      (gppudclk1, 0),
+    ]
+  }
+
+  #[test]
+  #[cfg(not(feature = "ruspiro_pi4"))]
+  fn pud_sequence_writes_the_control_value_before_the_clock() {
+    let _test = crate::exclusive_test();
+    pud_sequence(Pud::PullUp, 1 << 17, 0);
+    pud_sequence(Pud::PullDown, 0, 1 << 8);
+    pud_sequence(Pud::Disabled, 1 << 4, 1 << 21);
+    let expected = [
+      pud_cycle(0b10, 1 << 17, 0),
+      pud_cycle(0b01, 0, 1 << 8),
+      pud_cycle(0b00, 1 << 4, 1 << 21),
+    ]
+    .concat();
+    assert_eq!(mock::writes(), expected);
+  }
+
+  #[test]
+  #[cfg(not(feature = "ruspiro_pi4"))]
+  fn pin_pull_settings_clock_the_pin_of_its_bank() {
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    let pin = gpio.get_pin(17).unwrap().into_pud_up();
+    let _ = pin.into_pud_down().into_pud_disabled();
+    gpio.get_pin(40).unwrap().into_pud_up();
+    let expected = [
+      pud_cycle(0b10, 1 << 17, 0),
+      pud_cycle(0b01, 1 << 17, 0),
+      pud_cycle(0b00, 1 << 17, 0),
+      pud_cycle(0b10, 0, 1 << 8),
+    ]
+    .concat();
+    assert_eq!(mock::writes(), expected);
+  }
+
+  #[test]
+  #[cfg(feature = "ruspiro_pi4")]
+  fn pud_sequence_writes_the_bcm2711_field_of_each_pin() {
+    let _test = crate::exclusive_test();
+    pud_sequence(Pud::PullUp, (1 << 15) | (1 << 16), 0);
+    pud_sequence(Pud::PullDown, 0, 1 << 21);
+    assert_eq!(mock::peek(GPIO_BASE + 0xE4), 0b01 << 30);
+    assert_eq!(mock::peek(GPIO_BASE + 0xE8), 0b01);
+    assert_eq!(mock::peek(GPIO_BASE + 0xF0), 0b10 << 10);
+    // the pull up/down is applied without any clocked update cycle
+    assert_eq!(mock::writes().len(), 3);
+  }
+
+  #[test]
+  #[cfg(feature = "ruspiro_pi4")]
+  fn current_pud_reads_back_the_bcm2711_encoding() {
+    let _test = crate::exclusive_test();
+    for &pud in [Pud::PullUp, Pud::PullDown, Pud::Disabled].iter() {
+      pud_sequence(pud, 1 << 7, 1 << 8);
+      assert_eq!(current_pud(7), Some(pud));
+      assert_eq!(current_pud(40), Some(pud));
+    }
+    mock::poke(GPIO_BASE + 0xE4, 0b11 << 14);
+    assert_eq!(current_pud(7), None);
+  }
+}
@@ -0,0 +1,97 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Infrared remote control
+//!
+//! Send infrared remote control frames using the NEC protocol. The 38kHz carrier is generated on the output pin with
+//! busy waiting delays, so any output pin can be used to drive the IR LED.
+//!
+//! # Example
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! # fn doc() {
+//! let pin = GPIO.with_mut(|gpio| gpio.get_pin(18)).unwrap().into_output();
+//! // the core is running at 1.2GHz
+//! let ir = ir::NecTransmitter::new(pin, 1_200);
+//! ir.send(0x04, 0x08);
+//! # }
+//! ```
+
+use crate::interface::*;
+use crate::{function, Pin};
+
+/// Duration of the leading mark in µs
+const LEADER_MARK_US: u32 = 9_000;
+/// Duration of the space following the leading mark in µs
+const LEADER_SPACE_US: u32 = 4_500;
+/// Duration of the mark of each bit and the stop mark in µs
+const BIT_MARK_US: u32 = 562;
+/// Duration of the space of a ``0`` bit in µs
+const ZERO_SPACE_US: u32 = 562;
+/// Duration of the space of a ``1`` bit in µs
+const ONE_SPACE_US: u32 = 1_687;
+/// The carrier frequency in Hz
+const CARRIER_HZ: u32 = 38_000;
+
+/// Transmitter of NEC infrared remote control frames
+pub struct NecTransmitter<PUD> {
+  pin: Pin<function::Output, PUD>,
+  cycles_per_us: u32,
+}
+
+impl<PUD> NecTransmitter<PUD> {
+  /// Create a new transmitter driving the IR LED connected to the given output pin. The CPU cycles per µs are used to
+  /// calibrate the busy waiting delays of the carrier and frame timings.
+  pub fn new(pin: Pin<function::Output, PUD>, cycles_per_us: u32) -> Self {
+    pin.low();
+    Self { pin, cycles_per_us }
+  }
+
+  /// Release the output pin of the transmitter
+  pub fn into_inner(self) -> Pin<function::Output, PUD> {
+    self.pin
+  }
+
+  /// Send the NEC frame for the given address and command. The frame consists of the leading mark and space, the
+  /// address, the inverted address, the command and the inverted command with the least significant bit first and
+  /// a final stop mark.
+  /// **HINT*: This blocks for roughly 68ms and masks the IRQ's on the current core while doing so.
+  pub fn send(&self, addr: u8, cmd: u8) {
+    let data = u32::from_le_bytes([addr, !addr, cmd, !cmd]);
+    without_interrupts(|| {
+      self.mark(LEADER_MARK_US);
+      self.space(LEADER_SPACE_US);
+      for bit in 0..32 {
+        self.mark(BIT_MARK_US);
+        if data & (1 << bit) != 0 {
+          self.space(ONE_SPACE_US);
+        } else {
+          self.space(ZERO_SPACE_US);
+        }
+      }
+      self.mark(BIT_MARK_US);
+    });
+  }
+
+  /// Emit the modulated carrier for the given duration with a duty cycle of one third
+  fn mark(&self, us: u32) {
+    let period_cycles = self.cycles_per_us * 1_000_000 / CARRIER_HZ;
+    let high_cycles = period_cycles / 3;
+    let periods = us * CARRIER_HZ / 1_000_000;
+    for _ in 0..periods {
+      self.pin.high();
+      wait_cycles(high_cycles);
+      self.pin.low();
+      wait_cycles(period_cycles - high_cycles);
+    }
+  }
+
+  /// Keep the carrier off for the given duration
+  fn space(&self, us: u32) {
+    self.pin.low();
+    wait_cycles(us * self.cycles_per_us);
+  }
+}
@@ -11,7 +11,8 @@
 //!
 //! This crate provide as simple to use and safe abstraction of the GPIO's available on the Raspberry Pi 3. The GPIO
 //! configuration requires access to MMIO registers with a specific memory base address. As this might differ between
-//! different models the right address is choosen based on the given ``ruspiro_pi3`` feature while compiling.
+//! different models the right address is choosen based on the given ``ruspiro_pi3`` or ``ruspiro_pi4`` feature while
+//! compiling.
 //!
 //! # Usage
 //!
@@ -29,20 +30,100 @@
 //!
 //! # Features
 //!
+//! - ``alloc`` (default) Enables the event handler registration and the ``Gpio::reserve`` function that require a
+//!   heap allocator. Without this feature the pins could be acquired and configured before the allocator is
+//!   initialized, e.g. in early boot code.
 //! - ``ruspiro_pi3`` Ensures the proper MMIO base memory address is used for Raspberry Pi 3
+//! - ``ruspiro_pi4`` Ensures the proper MMIO base memory address is used for Raspberry Pi 4. This can not be combined
+//!   with the ``ruspiro_pi3`` feature. Other than ``ruspiro_pi3`` this is not forwarded to ``ruspiro-interrupt``, as
+//!   its current version has no Raspberry Pi 4 feature. The GPIO bank interrupts and thus the event handlers rely on
+//!   the interrupt controller support of that crate.
+//! - ``single-core`` Drops the ``Send`` requirement of the event handlers. This allows ``Rc``/``RefCell`` based state
+//!   to be used within the handlers, but is unsound if the GPIO is used from more than one core.
+//! - ``paranoid`` Verifies an output pin is still configured as output before it is driven and does not drive it if its
+//!   function has been changed behind the ``Pin``. This costs a register read for each write to the pin. The
+//!   ``Pin::try_high``, ``Pin::try_low`` and ``Pin::try_toggle`` variants always verify and return an error instead.
+//! - ``trace-events`` Records the most recent event dispatches for diagnostic purposes, see ``Gpio::recent_events``
+//! - ``soft-pwm`` Enables the software PWM of output pins, see ``Pin::into_pwm``. This occupies the compare channel 3
+//!   of the system timer and its interrupt.
+//! - ``event-coalescing`` Enables the event coalescing of the GPIO bank interrupts, see ``Gpio::set_event_coalescing``.
+//!   This occupies the compare channel 1 of the system timer and its interrupt.
+//! - ``embedded-hal`` Implements the digital pin traits of the ``embedded-hal`` crate for input and output pins, so
+//!   the pins could be used with generic device drivers.
+//! - ``mock-mmio`` Backs the MMIO registers with a static array instead of the peripheral addresses, see the ``mock``
+//!   module. This allows to run and unit test the pin logic on the development host, e.g. with ``cargo make test``.
+//!   The crate does not access the actual hardware with this feature, so it must not be used for the Raspberry Pi.
 //!
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
-use alloc::boxed::Box;
-use ruspiro_interrupt::{self as irq, Interrupt, IrqHandler, IsrSender};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "mock-mmio")]
+use mock::irq;
+#[cfg(not(feature = "mock-mmio"))]
+use ruspiro_interrupt as irq;
+use ruspiro_interrupt::Interrupt;
+#[cfg(feature = "alloc")]
+use ruspiro_interrupt::{IrqHandler, IsrSender};
 use ruspiro_singleton::Singleton;
 
 mod interface;
 use interface::*;
+pub use interface::{gpfsel_for, AltFunction, Function, GpioBank, Pud};
 mod pin;
 pub use self::pin::*;
+mod auto_off;
+pub use self::auto_off::*;
+mod bus;
+#[cfg(feature = "event-coalescing")]
+mod coalesce;
+mod configured;
+pub use self::configured::*;
+mod debounce;
+pub use self::debounce::*;
+mod events;
+pub use self::events::*;
+#[cfg(feature = "embedded-hal")]
+mod hal;
+mod heartbeat;
+pub use self::heartbeat::*;
+mod pins;
+pub use self::pins::*;
+mod polarity;
+pub use self::polarity::*;
+mod multiplex;
+mod output_array;
+pub use self::output_array::*;
+mod pads;
+pub use self::pads::*;
+mod pin_group;
+pub use self::pin_group::*;
+mod port;
+pub use self::port::*;
+#[cfg(feature = "soft-pwm")]
+mod pwm;
+#[cfg(feature = "soft-pwm")]
+pub use self::pwm::*;
+mod time;
+pub use self::time::*;
+mod transaction;
+pub use self::transaction::*;
+mod typed_pin;
+pub use self::typed_pin::*;
+#[cfg(feature = "trace-events")]
+mod trace;
+mod waveform;
+#[cfg(feature = "trace-events")]
+pub use self::trace::*;
 
+pub mod board;
 pub mod debug;
+pub mod ir;
+#[cfg(feature = "mock-mmio")]
+pub mod mock;
+pub mod peripheral;
 
 /// Static ``Singleton`` accessor to the GPIO peripheral. The ``Singleton`` ensures cross core mutual
 /// exclusive access.
@@ -50,7 +131,8 @@ pub static GPIO: Singleton<Gpio> = Singleton::<Gpio>::new(Gpio::new());
 
 /// GPIO peripheral representation
 pub struct Gpio {
-  used_pins: [bool; 40],
+  used_pins: [bool; GPIO_PIN_COUNT as usize],
+  reserved_pins: [bool; GPIO_PIN_COUNT as usize],
 }
 
 impl Gpio {
@@ -58,12 +140,13 @@ impl Gpio {
   /// pins uppon initialization
   pub const fn new() -> Self {
     Gpio {
-      used_pins: [false; 40],
+      used_pins: [false; GPIO_PIN_COUNT as usize],
+      reserved_pins: [false; GPIO_PIN_COUNT as usize],
     }
   }
 
   /// Get a new pin for further usage, the function of the pin is initially undefined/unknown
-  /// Returns an Err(str) if the pin is already in use, otherwise an Ok(Pin)
+  /// Returns an Err if the pin is already in use or reserved, otherwise an Ok(Pin)
   /// # Example
   /// ```no_run
   /// # use ruspiro_gpio::GPIO;
@@ -74,35 +157,352 @@ impl Gpio {
   /// # }
   /// ```
   pub fn get_pin(&mut self, num: u32) -> Result<Pin<function::Unknown, pud::Unknown>, GpioError> {
-    if self.used_pins[num as usize] {
-      Err(GpioError)
-    } else {
-      self.used_pins[num as usize] = true;
-      Ok(Pin::<function::Unknown, pud::Unknown>::new(num))
+    self.check_available(num)?;
+    self.used_pins[num as usize] = true;
+    Ok(Pin::<function::Unknown, pud::Unknown>::new(num))
+  }
+
+  /// Get a new pin for further usage and wait for it to become free if it is currently in use. The
+  /// ``spin`` callback is called between the attempts and could be used to yield to other tasks.
+  /// Returns an Err if the pin is reserved or out of range, as it would never become free.
+  /// **HINT*: This never returns if nothing releases the pin. As the [Gpio] is borrowed mutably the
+  /// pin could only be released from within the ``spin`` callback, or by another core if the
+  /// ``Gpio`` is not locked by the ``Singleton`` while waiting. Consider [Gpio::get_pin_retry]
+  /// with a bounded number of attempts instead.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let mut gpio = Gpio::new();
+  /// let pin = gpio.get_pin_blocking(17, || ()).unwrap();
+  /// # }
+  /// ```
+  pub fn get_pin_blocking<F: FnMut()>(
+    &mut self,
+    num: u32,
+    mut spin: F,
+  ) -> Result<Pin<function::Unknown, pud::Unknown>, GpioError> {
+    loop {
+      match self.get_pin(num) {
+        Err(GpioError::AlreadyInUse(_)) => spin(),
+        result => return result,
+      }
     }
   }
 
+  /// Get a new pin for further usage and retry the given number of times if it is currently in use.
+  /// The ``spin`` callback is called between the attempts and could be used to yield to other
+  /// tasks.
+  /// Returns an Err if the pin is still in use after all retries, is reserved or out of range.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let mut gpio = Gpio::new();
+  /// let pin = gpio.get_pin_retry(17, 100, || ()).unwrap();
+  /// # }
+  /// ```
+  pub fn get_pin_retry<F: FnMut()>(
+    &mut self,
+    num: u32,
+    retries: u32,
+    mut spin: F,
+  ) -> Result<Pin<function::Unknown, pud::Unknown>, GpioError> {
+    for _ in 0..retries {
+      match self.get_pin(num) {
+        Err(GpioError::AlreadyInUse(_)) => spin(),
+        result => return result,
+      }
+    }
+    self.get_pin(num)
+  }
+
   /// Release an used pin to allow re-usage for example with different configuration
-  /// The pin's state after release is considered unknown
+  /// The pin is switched to an input with disabled PullUp/Down, so a released output does not keep
+  /// driving e.g. a LED or a relay.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins, the pin is
+  /// not in use or a [Pin] owning it is still alive. Use e.g. [Pin::into_num] to give up the
+  /// ``Pin`` but keep the pin in use until it is released here.
   /// # Example
   /// ```no_run
   /// # use ruspiro_gpio::GPIO;
   /// # fn doc() {
-  /// GPIO.with_mut(|gpio| gpio.free_pin(17) );
+  /// GPIO.with_mut(|gpio| gpio.free_pin(17) ).unwrap();
   /// # }
   /// ```
-  pub fn free_pin(&mut self, num: u32) {
+  pub fn free_pin(&mut self, num: u32) -> Result<(), GpioError> {
+    self.free_pin_keep_state(num)?;
+    reset_pin(num);
+    Ok(())
+  }
+
+  /// Release an used pin like [Gpio::free_pin], but keep its function and PullUp/Down setting.
+  /// This allows e.g. an output to keep its level until the pin is acquired again.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// not in use. As long as a [Pin] or another handle owning the pin is alive the pin can not be
+  /// released by its number and an Err is returned as well, drop the handle to release the pin.
+  pub fn free_pin_keep_state(&mut self, num: u32) -> Result<(), GpioError> {
+    validate_pin(num)?;
+    self.reclaim_dropped_pins();
+    if CLAIMED_PINS.load(Ordering::Acquire) & (1 << num) != 0 {
+      return Err(GpioError::AlreadyInUse(num));
+    }
     // release the used pin
-    // TODO: reset also pin function or other settings?
-    if self.used_pins[num as usize] {
-      self.used_pins[num as usize] = false;
+    match self.used_pins.get_mut(num as usize) {
+      Some(used) if *used => {
+        *used = false;
+        Ok(())
+      }
+      _ => Err(GpioError::NotInUse(num)),
+    }
+  }
+
+  /// Acquire a pin, pass it to the closure and release it once the closure returns. Like with
+  /// [Gpio::free_pin] the pin is switched to an input with disabled PullUp/Down afterwards. This
+  /// scopes the usage of a pin tightly and guarantees its release.
+  /// A pin escaping the closure, e.g. by returning it, stays in use and keeps its state. It is
+  /// released like any other [Pin] once dropped.
+  /// Returns an Err if the pin is out of range, already in use or reserved, in this case the
+  /// closure is not executed.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn delay() {}
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     gpio.with_pin(17, |pin| {
+  ///         let led = pin.into_output();
+  ///         for _ in 0..5 {
+  ///             led.high();
+  ///             delay();
+  ///             led.low();
+  ///             delay();
+  ///         }
+  ///     })
+  /// })
+  /// .unwrap();
+  /// # }
+  /// ```
+  pub fn with_pin<R, F: FnOnce(Pin<function::Unknown, pud::Unknown>) -> R>(
+    &mut self,
+    num: u32,
+    f: F,
+  ) -> Result<R, GpioError> {
+    let result = f(self.get_pin(num)?);
+    // the pin is usually dropped at the end of the closure which already released it. A pin that
+    // escaped the closure is still owned by its handle and must not be reset
+    match self.free_pin_keep_state(num) {
+      Ok(()) | Err(GpioError::NotInUse(_)) => reset_pin(num),
+      Err(_) => (),
+    }
+    Ok(result)
+  }
+
+  /// Mark a pin as reserved without handing out a [Pin]. This prevents the pin from being acquired,
+  /// e.g. as it is claimed by the firmware for the SD card or the HDMI hotplug detection.
+  /// Returns an Err if the pin is out of range or already in use.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     gpio.mark_reserved(34).unwrap();
+  ///     assert!(gpio.get_pin(34).is_err());
+  /// });
+  /// # }
+  /// ```
+  pub fn mark_reserved(&mut self, num: u32) -> Result<(), GpioError> {
+    self.reclaim_dropped_pins();
+    match self.used_pins.get(num as usize) {
+      Some(false) => {
+        self.reserved_pins[num as usize] = true;
+        Ok(())
+      }
+      Some(true) => Err(GpioError::AlreadyInUse(num)),
+      None => Err(GpioError::OutOfRange(num)),
+    }
+  }
+
+  /// Release the pins that have been dropped since the last call. A dropped [Pin] can not release
+  /// itself directly as it might be dropped while the ``Gpio`` is already locked, e.g. within
+  /// ``GPIO.with_mut``, which would dead lock. It therefore only records its release which is
+  /// applied here before the pins in use are checked.
+  fn reclaim_dropped_pins(&mut self) {
+    let dropped = DROPPED_PINS.swap(0, Ordering::AcqRel);
+    if dropped != 0 {
+      CLAIMED_PINS.fetch_and(!dropped, Ordering::AcqRel);
+      for (num, used) in self.used_pins.iter_mut().enumerate() {
+        if dropped & (1 << num) != 0 {
+          *used = false;
+        }
+      }
+    }
+  }
+
+  /// Remove the reservation of a pin previously marked as reserved with [Gpio::mark_reserved]
+  pub fn unmark_reserved(&mut self, num: u32) {
+    if let Some(reserved) = self.reserved_pins.get_mut(num as usize) {
+      *reserved = false;
+    }
+  }
+
+  /// Check whether a pin is currently in use, without acquiring it. Returns ``false`` for pin
+  /// numbers out of the range of the available GPIO pins.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let in_use = GPIO.with_mut(|gpio| gpio.is_pin_used(17));
+  /// # }
+  /// ```
+  pub fn is_pin_used(&self, num: u32) -> bool {
+    validate_pin(num).is_ok()
+      && self.used_pins.get(num as usize).copied().unwrap_or(false)
+      // a dropped pin is no longer in use, even if it has not been released yet
+      && DROPPED_PINS.load(Ordering::Acquire) & (1 << num) == 0
+  }
+
+  /// Acquire all the pins given by the bit masks of bank 0 and bank 1. All pins are checked first
+  /// to not leave any pin acquired if one of them is not available.
+  /// Returns an Err if any of the pins is out of range, already in use or reserved.
+  pub(crate) fn acquire_all(&mut self, mask: [u32; 2]) -> Result<(), GpioError> {
+    let pins =
+      || BankPins::new(mask[0], GpioBank::Bank0).chain(BankPins::new(mask[1], GpioBank::Bank1));
+    for num in pins() {
+      self.check_available(num)?;
+    }
+    for num in pins() {
+      self.used_pins[num as usize] = true;
+    }
+    Ok(())
+  }
+
+  /// Check whether the pin could be acquired
+  pub(crate) fn check_available(&mut self, num: u32) -> Result<(), GpioError> {
+    validate_pin(num)?;
+    self.reclaim_dropped_pins();
+    match (
+      self.used_pins.get(num as usize),
+      self.reserved_pins.get(num as usize),
+    ) {
+      (Some(false), Some(false)) => Ok(()),
+      (Some(_), Some(true)) => Err(GpioError::Reserved(num)),
+      (Some(true), _) => Err(GpioError::AlreadyInUse(num)),
+      _ => Err(GpioError::OutOfRange(num)),
+    }
+  }
+
+  /// Acquire all the pins given and pass them to the closure provided. Once the closure returns all
+  /// pins are released again, even if the closure panics. This scopes the usage of a set of pins
+  /// tightly and guarantees their release.
+  /// Returns an Err if any of the pins is already in use or given more than once, in this case no pin
+  /// is acquired and the closure is not executed.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     gpio.reserve(&[2, 3], |pins| {
+  ///         // do something with the pins
+  ///     }).unwrap();
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn reserve<R, F>(&mut self, nums: &[u32], f: F) -> Result<R, GpioError>
+  where
+    F: FnOnce(&[Pin<function::Unknown, pud::Unknown>]) -> R,
+  {
+    self.acquire_all(pin_masks(nums)?)?;
+    let pins: Vec<_> = nums
+      .iter()
+      .map(|&num| Pin::<function::Unknown, pud::Unknown>::new(num))
+      .collect();
+    // the guard releases all pins when it goes out of scope, even if the closure panics
+    let guard = ReservationGuard {
+      gpio: self,
+      nums,
+      pins,
     };
+    Ok(f(&guard.pins))
+  }
+
+  /// Apply the PullUp/Down setting to all the pins given within a single pud change cycle. This is
+  /// much faster than configuring the pins one by one, e.g. for all lines of a bus.
+  /// Returns an Err if any of the pin numbers is out of the range of the available GPIO pins, in
+  /// this case no pin is configured.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| gpio.configure_pull(&[4, 5, 6, 7], Pud::PullUp)).unwrap();
+  /// # }
+  /// ```
+  pub fn configure_pull(&mut self, pins: &[u32], pud: Pud) -> Result<(), GpioError> {
+    let mut masks = [0u32; 2];
+    for &num in pins {
+      validate_pin(num)?;
+      masks[(num / 32) as usize] |= 1 << (num & 31);
+    }
+    pud_sequence(pud, masks[0], masks[1]);
+    Ok(())
+  }
+
+  /// Apply the PullUp/Down setting to all the pins given like [Gpio::configure_pull] and verify the
+  /// setting has been taken by reading it back. The pins that did not take the setting are retried
+  /// up to ``retries`` times.
+  /// Returns an Err if any of the pin numbers is out of the range of the available GPIO pins or a
+  /// pin did not take the setting after all retries.
+  /// **HINT*: Reading back the setting is not supported by the Raspberry Pi 3. On this model the
+  /// setting is applied once without any verification.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| gpio.configure_pull_verified(&[2, 3], Pud::PullUp, 3)).unwrap();
+  /// # }
+  /// ```
+  pub fn configure_pull_verified(
+    &mut self,
+    pins: &[u32],
+    pud: Pud,
+    retries: u32,
+  ) -> Result<(), GpioError> {
+    self.configure_pull(pins, pud)?;
+    for _ in 0..retries {
+      let mut masks = [0u32; 2];
+      for &num in pins {
+        if current_pud(num).map_or(false, |current| current != pud) {
+          masks[(num / 32) as usize] |= 1 << (num & 31);
+        }
+      }
+      if masks == [0, 0] {
+        return Ok(());
+      }
+      pud_sequence(pud, masks[0], masks[1]);
+    }
+    match pins
+      .iter()
+      .find(|&&num| current_pud(num).map_or(false, |current| current != pud))
+    {
+      Some(&num) => Err(GpioError::PudNotApplied(num)),
+      None => Ok(()),
+    }
   }
 
   /// Register an event handler to be executed whenever the event occurs on the GPIO [Pin] specified.
-  /// Event handler can only be registered for a ``Pin<Input,_>``.
+  /// The event could be a single [GpioEvent] or a combination of [GpioEvents] that all route to
+  /// this event handler. Any event detection previously armed for this pin is replaced. Passing
+  /// ``None`` disables the event detection for this pin.
+  /// Event handler can only be registered for a ``Pin<Input,_>`` or a pin with an alternative
+  /// function. The detection works on the physical level of the pin regardless of the function.
   /// The function/closure provided might be called several times. It's allowed to move mutable
-  /// context into the closure used.
+  /// context into the closure used. A pin has either a recurring or a oneshot event handler, so a
+  /// oneshot event handler registered for this pin is removed.
+  /// Returns an [EventHandlerToken] to deactivate exactly the events armed here with
+  /// [Gpio::remove_event_handler_by_token].
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// currently configured as output.
   /// **HINT*: Interrupts need to be globaly enabled.
   /// # Example
   /// ```no_run
@@ -118,50 +518,138 @@ impl Gpio {
   ///             counter += 1;
   ///             println!("GPIO Event raised {} time(s)", counter);
   ///         }
-  ///     );
+  ///     ).unwrap();
   /// });
   /// # }
   /// ```
-  pub fn register_recurring_event_handler<F: FnMut() + 'static + Send, PUD>(
+  #[cfg(feature = "alloc")]
+  pub fn register_recurring_event_handler<
+    E: Into<GpioEvents>,
+    F: RecurringEventHandler,
+    FUNC: EventCapable,
+    PUD,
+  >(
     &mut self,
-    pin: &Pin<function::Input, PUD>,
-    event: GpioEvent,
+    pin: &Pin<FUNC, PUD>,
+    event: E,
     function: F,
-  ) {
-    let slot = (pin.num & 31) as usize;
-    let bank = pin.num / 32;
+  ) -> Result<EventHandlerToken, GpioError> {
+    validate_pin(pin.num)?;
+    // edge and level detection is meaningless if the pin has been switched to an output in the
+    // meantime
+    if get_function(pin.num) == Function::Output {
+      return Err(GpioError::InvalidFunction(pin.num));
+    }
+    // access to the static array is safe as it happens only in the GPIO which has mutual
+    // exclusive access guarentees or inside the interrupt handler which is only active
+    // when there is no lock on the GPIO singleton.
+    unsafe { store_recurring_handler(pin.num, Box::new(function)) };
+    activate_bank_interrupt(pin_bank(pin.num));
+    let events = event.into();
+    set_detect_events(pin.num, events);
+    Ok(EventHandlerToken {
+      pin: pin.num,
+      events,
+    })
+  }
 
-    match bank {
-      0 => {
-        // access to the static array is safe as it happens only in the GPIO which has mutual
-        // exclusive access guarentees or inside the interrupt handler which is only active
-        // when there is no lock on the GPIO singleton.
-        unsafe {
-          BANK0_HANDLER_MC[slot].replace(Box::new(function));
-          // setting multi call clears single call
-          let _ = BANK0_HANDLER_SC[slot].take();
-        };
-        irq::activate(Interrupt::GpioBank0, None);
-      }
-      1 => {
-        // access to the static array is safe as it happens only in the GPIO which has mutual
-        // exclusive access guarentees or inside the interrupt handler which is only active
-        // when there is no lock on the GPIO singleton.
-        unsafe {
-          BANK1_HANDLER_MC[slot].replace(Box::new(function));
-          // setting multi call clears single call
-          let _ = BANK1_HANDLER_SC[slot].take();
-        };
-        irq::activate(Interrupt::GpioBank1, None);
+  /// Register an event handler like [Gpio::register_recurring_event_handler] that is passed the
+  /// number of the pin the event occured on. This allows the same handler to be registered for
+  /// several pins and to tell them apart, e.g. for the rows of a keypad.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// currently configured as output.
+  /// **HINT*: Interrupts need to be globaly enabled.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let rows = [
+  ///         gpio.get_pin(5).unwrap().into_input(),
+  ///         gpio.get_pin(6).unwrap().into_input(),
+  ///     ];
+  ///     let key_pressed = |num: u32| println!("key in row of GPIO {} pressed", num);
+  ///     for row in rows.iter() {
+  ///         gpio.register_recurring_event_handler_with_pin(row, GpioEvent::FallingEdge, key_pressed)
+  ///             .unwrap();
+  ///     }
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn register_recurring_event_handler_with_pin<
+    E: Into<GpioEvents>,
+    F: RecurringPinEventHandler,
+    FUNC: EventCapable,
+    PUD,
+  >(
+    &mut self,
+    pin: &Pin<FUNC, PUD>,
+    event: E,
+    mut function: F,
+  ) -> Result<EventHandlerToken, GpioError> {
+    let num = pin.num;
+    self.register_recurring_event_handler(pin, event, move || function(num))
+  }
+
+  /// Register an event handler like [Gpio::register_recurring_event_handler] that ignores the
+  /// events caused by a bouncing contact, e.g. of a mechanical button. Each event is time stamped
+  /// with the given time source. The handler is only called if the previous event occured at
+  /// least ``debounce_us`` micro seconds before, so it is called once for the first edge of a
+  /// bouncing contact and again only after the contact has been settled for ``debounce_us``. The
+  /// very first event always calls the handler.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// currently configured as output.
+  /// **HINT*: Interrupts need to be globaly enabled and the time source needs to be running.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl TimeSource for Timer { fn now_us(&self) -> u64 { 0 } }
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let button = gpio.get_pin(26).unwrap().into_input().into_pud_up();
+  ///     gpio.register_debounced_event_handler(&button, GpioEvent::FallingEdge, 20_000, Timer, || {
+  ///         println!("button pressed");
+  ///     })
+  ///     .unwrap();
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn register_debounced_event_handler<
+    E: Into<GpioEvents>,
+    T: TimeSource + Send + 'static,
+    F: RecurringEventHandler,
+    FUNC: EventCapable,
+    PUD,
+  >(
+    &mut self,
+    pin: &Pin<FUNC, PUD>,
+    event: E,
+    debounce_us: u64,
+    timer: T,
+    mut function: F,
+  ) -> Result<EventHandlerToken, GpioError> {
+    let mut last_event: Option<u64> = None;
+    self.register_recurring_event_handler(pin, event, move || {
+      let now = timer.now_us();
+      if last_event.map_or(true, |last| timer.elapsed_us(last, now) >= debounce_us) {
+        function();
       }
-      _ => (),
-    };
-    activate_detect_event(pin.num, event);
+      last_event = Some(now);
+    })
   }
 
   /// Register an event handler to be executed at the first occurence of the specified event on
-  /// the given GPIO [Pin]. The event handler can only be registered for a ``Pin<Input,_>``.
+  /// the given GPIO [Pin]. The event handler can only be registered for a ``Pin<Input,_>`` or a
+  /// pin with an alternative function.
   /// The function/closure provided will be called only once.
+  /// Any event detection previously armed for this pin is replaced. Passing ``None`` as event
+  /// disables the event detection for this pin. A pin has either a oneshot or a recurring event
+  /// handler, so a recurring event handler registered for this pin is removed.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// currently configured as output.
   /// **HINT*: Interrupts need to be globaly enabled.
   /// # Example
   /// ```no_run
@@ -175,62 +663,59 @@ impl Gpio {
   ///         move || {
   ///             println!("GPIO Event raised");
   ///         }
-  ///     );
+  ///     ).unwrap();
   /// });
   /// # }
   /// ```
-  pub fn register_oneshot_event_handler<F: FnOnce() + 'static + Send, PUD>(
+  #[cfg(feature = "alloc")]
+  pub fn register_oneshot_event_handler<
+    E: Into<GpioEvents>,
+    F: OneshotEventHandler,
+    FUNC: EventCapable,
+    PUD,
+  >(
     &mut self,
-    pin: &Pin<function::Input, PUD>,
-    event: GpioEvent,
+    pin: &Pin<FUNC, PUD>,
+    event: E,
     function: F,
-  ) {
-    let slot = (pin.num & 31) as usize;
-    let bank = pin.num / 32;
-
-    match bank {
-      0 => {
-        // access to the static array is safe as it happens only in the GPIO which has mutual
-        // exclusive access guarentees or inside the interrupt handler which is only active
-        // when there is no lock on the GPIO singleton.
-        unsafe {
-          BANK0_HANDLER_SC[slot].replace(Box::new(function));
-          // setting single call clears multi call
-          let _ = BANK0_HANDLER_MC[slot].take();
-        };
-        irq::activate(Interrupt::GpioBank0, None);
-      }
-      1 => {
-        // access to the static array is safe as it happens only in the GPIO which has mutual
-        // exclusive access guarentees or inside the interrupt handler which is only active
-        // when there is no lock on the GPIO singleton.
-        unsafe {
-          BANK1_HANDLER_SC[slot].replace(Box::new(function));
-          // setting single call clears multi call
-          let _ = BANK1_HANDLER_MC[slot].take();
-        };
-        irq::activate(Interrupt::GpioBank1, None);
-      }
-      _ => (),
-    };
+  ) -> Result<(), GpioError> {
+    validate_pin(pin.num)?;
+    // edge and level detection is meaningless if the pin has been switched to an output in the
+    // meantime
+    if get_function(pin.num) == Function::Output {
+      return Err(GpioError::InvalidFunction(pin.num));
+    }
+    // access to the static array is safe as it happens only in the GPIO which has mutual
+    // exclusive access guarentees or inside the interrupt handler which is only active
+    // when there is no lock on the GPIO singleton.
+    unsafe { store_oneshot_handler(pin.num, Box::new(function)) };
+    activate_bank_interrupt(pin_bank(pin.num));
 
-    activate_detect_event(pin.num, event);
+    set_detect_events(pin.num, event.into());
+    Ok(())
   }
 
   /// Remove the event handler and deactivate any event detection for the GPIO [Pin] specified.
-  /// Removing event handler is only available on a ``Pin<Input,_>``.
+  /// Removing event handler is only available on a ``Pin<Input,_>`` or a pin with an alternative
+  /// function.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins.
   /// # Example
   /// ```no_run
   /// # use ruspiro_gpio::*;
   /// # fn doc() {
   /// GPIO.with_mut(|gpio| {
   ///     let pin = gpio.get_pin(12).unwrap().into_input();
-  ///     gpio.remove_event_handler(&pin);
+  ///     gpio.remove_event_handler(&pin).unwrap();
   /// });
   /// # }
   /// ```
-  pub fn remove_event_handler<PUD>(&mut self, pin: &Pin<function::Input, PUD>) {
-    let slot = (pin.num & 31) as usize;
+  #[cfg(feature = "alloc")]
+  pub fn remove_event_handler<FUNC: EventCapable, PUD>(
+    &mut self,
+    pin: &Pin<FUNC, PUD>,
+  ) -> Result<(), GpioError> {
+    validate_pin(pin.num)?;
+    let slot = handler_slot(pin.num);
     let bank = pin.num / 32;
 
     match bank {
@@ -250,10 +735,636 @@ impl Gpio {
     };
 
     deactivate_all_detect_events(pin.num);
+    Ok(())
+  }
+
+  /// Activate the detection of the given events for the pin in addition to the events already
+  /// active for it. Other than the registration of an event handler, which arms exactly the events
+  /// given, this accumulates the events, e.g. to react to an edge and a level with the same event
+  /// handler. [Gpio::remove_event_handler] deactivates all of them.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins or the pin is
+  /// currently configured as output.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(12).unwrap().into_input();
+  ///     gpio.register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || ()).unwrap();
+  ///     // keep the rising edge detection and also detect a high level
+  ///     gpio.activate_detect_events(&pin, GpioEvent::High).unwrap();
+  /// });
+  /// # }
+  /// ```
+  pub fn activate_detect_events<E: Into<GpioEvents>, FUNC: EventCapable, PUD>(
+    &mut self,
+    pin: &Pin<FUNC, PUD>,
+    events: E,
+  ) -> Result<(), GpioError> {
+    validate_pin(pin.num)?;
+    if get_function(pin.num) == Function::Output {
+      return Err(GpioError::InvalidFunction(pin.num));
+    }
+    activate_detect_events(pin.num, events.into());
+    Ok(())
   }
+
+  /// Deactivate exactly the detect events armed by the registration of the event handler the token
+  /// has been returned for. The event handler of the pin is removed once no detect event is active
+  /// for the pin anymore.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(12).unwrap().into_input();
+  ///     let token = gpio
+  ///         .register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || ())
+  ///         .unwrap();
+  ///     gpio.activate_detect_events(&pin, GpioEvent::High).unwrap();
+  ///     // the high level detection stays active
+  ///     gpio.remove_event_handler_by_token(token);
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn remove_event_handler_by_token(&mut self, token: EventHandlerToken) {
+    for event in token.events.iter() {
+      deactivate_detect_event(token.pin, event);
+    }
+    let slot = handler_slot(token.pin);
+    let bank = pin_bank(token.pin);
+    let still_active = get_detect_enables(bank)
+      .iter()
+      .any(|enables| enables & (1 << slot) != 0);
+    if !still_active {
+      // access to the static array is safe as it happens only in the GPIO which has mutual
+      // exclusive access guarentees or inside the interrupt handler which is only active
+      // when there is no lock on the GPIO singleton.
+      unsafe {
+        match bank {
+          GpioBank::Bank0 => {
+            let _ = BANK0_HANDLER_SC[slot].take();
+            let _ = BANK0_HANDLER_MC[slot].take();
+          }
+          GpioBank::Bank1 => {
+            let _ = BANK1_HANDLER_SC[slot].take();
+            let _ = BANK1_HANDLER_MC[slot].take();
+          }
+        }
+      };
+    }
+  }
+
+  /// Deactivate all event detections and remove all event handlers of the given GPIO bank. This
+  /// stops all GPIO events of this bank, e.g. before entering a critical section or shutting down.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| gpio.disable_all_events(GpioBank::Bank0));
+  /// # }
+  /// ```
+  pub fn disable_all_events(&mut self, bank: GpioBank) {
+    set_detect_enables(bank, [0; 6]);
+    // access to the static array is safe as it happens only in the GPIO which has mutual
+    // exclusive access guarentees or inside the interrupt handler which is only active
+    // when there is no lock on the GPIO singleton.
+    #[cfg(feature = "alloc")]
+    unsafe {
+      match bank {
+        GpioBank::Bank0 => {
+          BANK0_HANDLER_SC
+            .iter_mut()
+            .for_each(|handler| *handler = None);
+          BANK0_HANDLER_MC
+            .iter_mut()
+            .for_each(|handler| *handler = None);
+        }
+        GpioBank::Bank1 => {
+          BANK1_HANDLER_SC
+            .iter_mut()
+            .for_each(|handler| *handler = None);
+          BANK1_HANDLER_MC
+            .iter_mut()
+            .for_each(|handler| *handler = None);
+        }
+      }
+    };
+  }
+
+  /// Run the closure with the GPIO bank interrupts disabled. This provides a critical section for
+  /// reconfigurations that consist of several steps and should not be interrupted by an event
+  /// dispatch in between. Afterwards the bank interrupts that were active before or that got
+  /// activated by registering an event handler within the closure are enabled again.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(12).unwrap().into_input();
+  ///     gpio.with_interrupts_disabled(|gpio| {
+  ///         gpio.remove_event_handler(&pin).unwrap();
+  ///         gpio.register_recurring_event_handler(
+  ///             &pin,
+  ///             GpioEvent::FallingEdge,
+  ///             || println!("GPIO Event raised")
+  ///         ).unwrap();
+  ///     });
+  /// });
+  /// # }
+  /// ```
+  pub fn with_interrupts_disabled<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> R {
+    // a nested call keeps the interrupts disabled until the outermost call returns
+    let suspended = BANK_IRQ_SUSPENDED.swap(true, Ordering::AcqRel);
+    if !suspended {
+      if BANK_IRQ_ACTIVE[0].load(Ordering::Acquire) {
+        irq::deactivate(Interrupt::GpioBank0);
+      }
+      if BANK_IRQ_ACTIVE[1].load(Ordering::Acquire) {
+        irq::deactivate(Interrupt::GpioBank1);
+      }
+    }
+    // the guard restores the interrupts when it goes out of scope, even if the closure panics
+    let _guard = InterruptGuard { suspended };
+    f(self)
+  }
+
+  /// Returns whether any event detection is armed for any pin of the given GPIO bank. This could be
+  /// used to decide whether the bank interrupt could be safely disabled.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let armed = GPIO.with_mut(|gpio| gpio.bank_has_events(GpioBank::Bank1));
+  /// # }
+  /// ```
+  pub fn bank_has_events(&self, bank: GpioBank) -> bool {
+    get_detect_enables(bank).iter().any(|&enables| enables != 0)
+  }
+
+  /// Returns whether any event detection is armed for any pin of both GPIO banks
+  pub fn any_events_active(&self) -> bool {
+    self.bank_has_events(GpioBank::Bank0) || self.bank_has_events(GpioBank::Bank1)
+  }
+
+  /// Register an event handler for the GPIO [Pin] specified that is only active while the given
+  /// ``body`` is executed. Once the ``body`` returns the event handler is removed and the event
+  /// detection for this pin is deactivated again. This ensures no event handler outlives the scope
+  /// that is interested in the event.
+  /// Returns an Err if the event handler could not be registered, the ``body`` is not executed in
+  /// this case.
+  /// **HINT*: As long as the [Gpio] is locked the interrupts are disabled. Events that occur while
+  /// the ``body`` is executed are only dispatched if the interrupts are enabled meanwhile. Events
+  /// still pending once the ``body`` returns are discarded together with the event handler.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(12).unwrap().into_input();
+  ///     let result = gpio.with_event(
+  ///         &pin,
+  ///         GpioEvent::RisingEdge,
+  ///         || println!("GPIO Event raised"),
+  ///         || {
+  ///             // do something while the event handler is active
+  ///             42
+  ///         }
+  ///     ).unwrap();
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn with_event<R, E, F, B, FUNC: EventCapable, PUD>(
+    &mut self,
+    pin: &Pin<FUNC, PUD>,
+    event: E,
+    function: F,
+    body: B,
+  ) -> Result<R, GpioError>
+  where
+    E: Into<GpioEvents>,
+    F: RecurringEventHandler,
+    B: FnOnce() -> R,
+  {
+    self.register_recurring_event_handler(pin, event, function)?;
+    // the guard removes the event handler when it goes out of scope, even if the body panics
+    let _guard = EventHandlerGuard { gpio: self, pin };
+    Ok(body())
+  }
+
+  /// Mirror the level of the input pin onto the output pin in software. A recurring event handler
+  /// for both edges of the input drives the output to the level of the input on every edge. This
+  /// is handy to route a signal to a different pin while debugging.
+  /// Returns an Err if the event handler could not be registered for the input.
+  /// **HINT*: The output pin is driven by its number, so it needs to stay configured as output as
+  /// long as it is bound. The level tracked by the output [Pin] is not updated.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let input = gpio.get_pin(17).unwrap().into_input();
+  ///     let output = gpio.get_pin(27).unwrap().into_output();
+  ///     gpio.bind_input_to_output(&input, &output).unwrap();
+  ///     // ...
+  ///     gpio.unbind(&input).unwrap();
+  /// });
+  /// # }
+  /// ```
+  #[cfg(feature = "alloc")]
+  pub fn bind_input_to_output<FUNC: EventCapable, PUDI, PUDO>(
+    &mut self,
+    input: &Pin<FUNC, PUDI>,
+    output: &Pin<function::Output, PUDO>,
+  ) -> Result<(), GpioError> {
+    let input_bank = pin_bank(input.num);
+    let input_mask = 1 << (input.num & 31);
+    let output_bank = pin_bank(output.num);
+    let output_mask = 1 << (output.num & 31);
+    let mirror = move || {
+      if get_levels(input_bank) & input_mask != 0 {
+        set_pins(output_bank, output_mask);
+      } else {
+        clear_pins(output_bank, output_mask);
+      }
+    };
+    self.register_recurring_event_handler(input, GpioEvent::BothEdges, mirror)?;
+    // bring the output in line with the input level that was present before the first edge
+    mirror();
+    Ok(())
+  }
+
+  /// Remove the binding of the input pin established with [Gpio::bind_input_to_output]. The
+  /// output keeps the level last mirrored.
+  #[cfg(feature = "alloc")]
+  pub fn unbind<FUNC: EventCapable, PUD>(
+    &mut self,
+    input: &Pin<FUNC, PUD>,
+  ) -> Result<(), GpioError> {
+    self.remove_event_handler(input)
+  }
+
+  /// Check whether an event has been detected for the GPIO [Pin] specified since the last check
+  /// and acknowledge it. This allows polling for events without any interrupt setup.
+  /// **HINT*: The event detect status of a pin is shared with the interrupt handler. Once an event
+  /// handler is registered for this pin the interrupt handler will acknowledge the event before it
+  /// could be seen here. So either register an event handler or poll for the event but not both.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(12).unwrap().into_input();
+  ///     if gpio.take_pending_event(&pin) {
+  ///         println!("GPIO Event raised");
+  ///     }
+  /// });
+  /// # }
+  /// ```
+  pub fn take_pending_event<FUNC: EventCapable, PUD>(&mut self, pin: &Pin<FUNC, PUD>) -> bool {
+    let bank = pin_bank(pin.num);
+    let pending = get_detected_events(bank) & (1 << (pin.num & 31));
+    if pending != 0 {
+      acknowledge_detected_events(pending, bank);
+      true
+    } else {
+      false
+    }
+  }
+
+  /// Get the number of GPIO pins available on the Raspberry Pi model this crate is build for.
+  /// This could be used to size per-pin arrays.
+  pub fn pin_count(&self) -> u32 {
+    GPIO_PIN_COUNT
+  }
+
+  /// Read the current function of a specific GPIO pin, regardless of whether the pin is in use.
+  /// This could be used to verify the boot firmware left a pin in the expected state.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// // verify the UART0 RXD pin is configured as expected
+  /// let function = GPIO.with_mut(|gpio| gpio.pin_function(15)).unwrap();
+  /// assert_eq!(function, Function::Alt0);
+  /// # }
+  /// ```
+  pub fn pin_function(&self, num: u32) -> Result<Function, GpioError> {
+    validate_pin(num)?;
+    Ok(get_function(num))
+  }
+
+  /// Read back the current PullUp/Down setting of a specific GPIO pin, regardless of whether the
+  /// pin is in use. This could be used to diagnose a floating input. Only the Raspberry Pi 4
+  /// allows to read back the setting, on the other models this is always ``None``.
+  /// Returns an Err if the pin number is out of the range of the available GPIO pins.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// if let Some(pud) = GPIO.with_mut(|gpio| gpio.pin_pud(17)).unwrap() {
+  ///     println!("GPIO 17 is configured with {:?}", pud);
+  /// }
+  /// # }
+  /// ```
+  pub fn pin_pud(&self, num: u32) -> Result<Option<Pud>, GpioError> {
+    validate_pin(num)?;
+    Ok(current_pud(num))
+  }
+
+  /// Read the current function of all GPIO pins. The entry at index ``n`` of the returned array
+  /// is the function of GPIO ``n``. This could be used to verify a known pin configuration at
+  /// startup.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let functions = GPIO.with_mut(|gpio| gpio.function_map());
+  /// // verify the UART0 TXD pin is configured as expected
+  /// assert_eq!(functions[14], Function::Alt0);
+  /// # }
+  /// ```
+  pub fn function_map(&self) -> [Function; GPIO_PIN_COUNT as usize] {
+    let fsel = get_function_selects();
+    let mut functions = [Function::Input; GPIO_PIN_COUNT as usize];
+    for (num, function) in functions.iter_mut().enumerate() {
+      let (reg, shift) = gpfsel_for(num as u32);
+      *function = Function::from_bits(fsel[reg] >> shift);
+    }
+    functions
+  }
+
+  /// Read the level of all GPIO pins of both banks as a coherent snapshot. Bit ``n`` of the returned
+  /// value is the level of GPIO ``n``. The two level registers are read back-to-back with the IRQ's
+  /// masked on the current core, so no interrupt handler could change any pin state between the
+  /// reads. This is only the window of the two register reads. It could be used to correlate the
+  /// levels of pins across the banks, e.g. for logic analysis.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let levels = GPIO.with_mut(|gpio| gpio.snapshot_and_freeze());
+  /// let gpio40_high = levels & (1 << 40) != 0;
+  /// # }
+  /// ```
+  pub fn snapshot_and_freeze(&self) -> u64 {
+    let (bank0, bank1) =
+      without_interrupts(|| (get_levels(GpioBank::Bank0), get_levels(GpioBank::Bank1)));
+    (bank1 as u64) << 32 | bank0 as u64
+  }
+
+  /// Get the Raspberry Pi model this crate is build for
+  pub fn model(&self) -> PiModel {
+    #[cfg(feature = "ruspiro_pi3")]
+    let model = PiModel::Pi3;
+    #[cfg(feature = "ruspiro_pi4")]
+    let model = PiModel::Pi4;
+    model
+  }
+
+  /// Configure the number of cycles to wait for the control signal to settle within the pull
+  /// up/down update cycle. The default of 150 cycles is required by the BCM2835 datasheet and
+  /// only needs to be changed if the core clock configuration makes it too short or unnecessarily
+  /// long. The setting applies to all pull up/down changes made afterwards.
+  /// **HINT*: The Raspberry Pi 4 does not use an update cycle, so the setting has no effect there.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| gpio.set_pud_settle_cycles(300));
+  /// # }
+  /// ```
+  pub fn set_pud_settle_cycles(&mut self, cycles: u32) {
+    PUD_SETTLE.store(cycles, Ordering::Release);
+  }
+
+  /// Get the number of cycles waited within the pull up/down update cycle
+  pub fn pud_settle_cycles(&self) -> u32 {
+    PUD_SETTLE.load(Ordering::Acquire)
+  }
+}
+
+/// Validate the pin number is within the range of the available GPIO pins. Every public entry point
+/// taking a pin number uses this before the number is used to access any register or storage.
+pub(crate) fn validate_pin(num: u32) -> Result<(), GpioError> {
+  if num < GPIO_PIN_COUNT {
+    Ok(())
+  } else {
+    Err(GpioError::OutOfRange(num))
+  }
+}
+
+/// Get the bit masks of bank 0 and bank 1 flagging the pins given, e.g. to acquire them with
+/// [Gpio::acquire_all].
+/// Returns an Err if any of the pins is out of range or given more than once.
+pub(crate) fn pin_masks(nums: &[u32]) -> Result<[u32; 2], GpioError> {
+  let mut masks = [0u32; 2];
+  for &num in nums {
+    validate_pin(num)?;
+    let mask = &mut masks[(num / 32) as usize];
+    if *mask & (1 << (num & 31)) != 0 {
+      return Err(GpioError::AlreadyInUse(num));
+    }
+    *mask |= 1 << (num & 31);
+  }
+  Ok(masks)
+}
+
+/// Return a released pin into a high-impedance state, an input with disabled PullUp/Down
+fn reset_pin(num: u32) {
+  set_function(num, Function::Input);
+  let mask = 1 << (num & 31);
+  match pin_bank(num) {
+    GpioBank::Bank0 => pud_sequence(Pud::Disabled, mask, 0),
+    GpioBank::Bank1 => pud_sequence(Pud::Disabled, 0, mask),
+  }
+}
+
+/// Record the release of a dropped pin, see [Gpio::reclaim_dropped_pins]. This does not lock the
+/// [GPIO] and is therefore safe to call while it is locked.
+pub(crate) fn release_dropped_pin(num: u32) {
+  DROPPED_PINS.fetch_or(1 << num, Ordering::AcqRel);
+}
+
+/// Record that a handle owning the pin, like a [Pin], has been handed out. While the handle is
+/// alive the pin can not be released by its number, so a stale handle could not release the pin
+/// once it has been handed out again. The claim ends when the handle is dropped.
+pub(crate) fn claim_pin(num: u32) {
+  CLAIMED_PINS.fetch_or(1 << num, Ordering::AcqRel);
+}
+
+/// End the claim of a handle that is consumed without releasing the pin, e.g. by ``into_num``. The
+/// pin stays in use and could be released by its number afterwards.
+pub(crate) fn unclaim_pin(num: u32) {
+  CLAIMED_PINS.fetch_and(!(1 << num), Ordering::AcqRel);
+}
+
+/// Guard that releases a set of pins once it goes out of scope
+#[cfg(feature = "alloc")]
+struct ReservationGuard<'a> {
+  gpio: &'a mut Gpio,
+  nums: &'a [u32],
+  pins: Vec<Pin<function::Unknown, pud::Unknown>>,
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for ReservationGuard<'_> {
+  fn drop(&mut self) {
+    // drop the pins first, a pin can not be released by its number while it is alive
+    self.pins.clear();
+    for &num in self.nums {
+      let _ = self.gpio.free_pin_keep_state(num);
+      reset_pin(num);
+    }
+  }
+}
+
+/// Guard that removes the event handler of a GPIO [Pin] once it goes out of scope
+#[cfg(feature = "alloc")]
+struct EventHandlerGuard<'a, FUNC: EventCapable, PUD> {
+  gpio: &'a mut Gpio,
+  pin: &'a Pin<FUNC, PUD>,
+}
+
+#[cfg(feature = "alloc")]
+impl<FUNC: EventCapable, PUD> Drop for EventHandlerGuard<'_, FUNC, PUD> {
+  fn drop(&mut self) {
+    let _ = self.gpio.remove_event_handler(self.pin);
+    // an event detected within the scope must not raise the bank interrupt once the handler is gone
+    self.gpio.take_pending_event(self.pin);
+  }
+}
+
+/// Guard that enables the GPIO bank interrupts again once it goes out of scope
+struct InterruptGuard {
+  /// whether the interrupts have already been suspended when the guard was created
+  suspended: bool,
+}
+
+impl Drop for InterruptGuard {
+  fn drop(&mut self) {
+    if !self.suspended {
+      BANK_IRQ_SUSPENDED.store(false, Ordering::Release);
+      resume_bank_interrupt(GpioBank::Bank0);
+      resume_bank_interrupt(GpioBank::Bank1);
+    }
+  }
+}
+
+/// Activate the interrupt of the given GPIO bank. While the interrupts are suspended this is only
+/// recorded and the interrupt is activated once they are resumed.
+#[cfg(feature = "alloc")]
+fn activate_bank_interrupt(bank: GpioBank) {
+  BANK_IRQ_ACTIVE[bank as usize].store(true, Ordering::Release);
+  resume_bank_interrupt(bank);
 }
 
+/// Activate the interrupt of the given GPIO bank again if it has been activated and is neither
+/// suspended by [Gpio::with_interrupts_disabled] nor held back by the event coalescing
+fn resume_bank_interrupt(bank: GpioBank) {
+  let index = bank as usize;
+  if BANK_IRQ_ACTIVE[index].load(Ordering::Acquire)
+    && !BANK_IRQ_SUSPENDED.load(Ordering::Acquire)
+    && !BANK_IRQ_HELD[index].load(Ordering::Acquire)
+  {
+    match bank {
+      GpioBank::Bank0 => irq::activate(Interrupt::GpioBank0, None),
+      GpioBank::Bank1 => irq::activate(Interrupt::GpioBank1, None),
+    }
+  }
+}
+
+/// Hold back the interrupt of the given GPIO bank until [release_bank_interrupt] is called. The
+/// events detected in the meantime stay latched in the event detect status of the bank.
+#[cfg(feature = "event-coalescing")]
+pub(crate) fn hold_bank_interrupt(bank: GpioBank) {
+  BANK_IRQ_HELD[bank as usize].store(true, Ordering::Release);
+  match bank {
+    GpioBank::Bank0 => irq::deactivate(Interrupt::GpioBank0),
+    GpioBank::Bank1 => irq::deactivate(Interrupt::GpioBank1),
+  }
+}
+
+/// Stop holding back the interrupt of the given GPIO bank, see [hold_bank_interrupt]
+#[cfg(feature = "event-coalescing")]
+pub(crate) fn release_bank_interrupt(bank: GpioBank) {
+  BANK_IRQ_HELD[bank as usize].store(false, Ordering::Release);
+  resume_bank_interrupt(bank);
+}
+
+/// Returns whether the interrupt of the given GPIO bank is held back, see [hold_bank_interrupt]
+#[cfg(feature = "event-coalescing")]
+pub(crate) fn is_bank_interrupt_held(bank: GpioBank) -> bool {
+  BANK_IRQ_HELD[bank as usize].load(Ordering::Acquire)
+}
+
+/// Get the GPIO pin numbers an event has been detected for at the given bank. This could be used
+/// by custom interrupt handler implementations to iterate over the triggering pins.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// for pin in triggered_pins(GpioBank::Bank0) {
+///     println!("event detected at GPIO {}", pin);
+/// }
+/// # }
+/// ```
+pub fn triggered_pins(bank: GpioBank) -> impl Iterator<Item = u32> {
+  BankPins::new(get_detected_events(bank), bank)
+}
+
+/// The functions/closures that could be registered as recurring event handler. The handler is
+/// called from within the interrupt handler that might run on any core and is therefore required
+/// to be ``Send``, unless the ``single-core`` feature is active.
+#[cfg(not(feature = "single-core"))]
+pub trait RecurringEventHandler: FnMut() + Send + 'static {}
+#[cfg(not(feature = "single-core"))]
+impl<T: FnMut() + Send + 'static> RecurringEventHandler for T {}
+
+/// The functions/closures that could be registered as recurring event handler. With the
+/// ``single-core`` feature active the handler is not required to be ``Send``.
+#[cfg(feature = "single-core")]
+pub trait RecurringEventHandler: FnMut() + 'static {}
+#[cfg(feature = "single-core")]
+impl<T: FnMut() + 'static> RecurringEventHandler for T {}
+
+/// The functions/closures that could be registered as recurring event handler being passed the
+/// number of the pin the event occured on. The handler is called from within the interrupt handler
+/// that might run on any core and is therefore required to be ``Send``, unless the ``single-core``
+/// feature is active.
+#[cfg(not(feature = "single-core"))]
+pub trait RecurringPinEventHandler: FnMut(u32) + Send + 'static {}
+#[cfg(not(feature = "single-core"))]
+impl<T: FnMut(u32) + Send + 'static> RecurringPinEventHandler for T {}
+
+/// The functions/closures that could be registered as recurring event handler being passed the
+/// number of the pin the event occured on. With the ``single-core`` feature active the handler is
+/// not required to be ``Send``.
+#[cfg(feature = "single-core")]
+pub trait RecurringPinEventHandler: FnMut(u32) + 'static {}
+#[cfg(feature = "single-core")]
+impl<T: FnMut(u32) + 'static> RecurringPinEventHandler for T {}
+
+/// The functions/closures that could be registered as oneshot event handler. The handler is
+/// called from within the interrupt handler that might run on any core and is therefore required
+/// to be ``Send``, unless the ``single-core`` feature is active.
+#[cfg(not(feature = "single-core"))]
+pub trait OneshotEventHandler: FnOnce() + Send + 'static {}
+#[cfg(not(feature = "single-core"))]
+impl<T: FnOnce() + Send + 'static> OneshotEventHandler for T {}
+
+/// The functions/closures that could be registered as oneshot event handler. With the
+/// ``single-core`` feature active the handler is not required to be ``Send``.
+#[cfg(feature = "single-core")]
+pub trait OneshotEventHandler: FnOnce() + 'static {}
+#[cfg(feature = "single-core")]
+impl<T: FnOnce() + 'static> OneshotEventHandler for T {}
+
 /// The different GPIO detect events, an event handler can be registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GpioEvent {
   /// Event triggered when the level changes from low to high
   RisingEdge,
@@ -276,24 +1387,125 @@ pub enum GpioEvent {
   AsyncBothEdges,
 }
 
-/// The error type that will be returned on issues with accessing the GPIO peripheral
-pub struct GpioError;
+impl GpioEvent {
+  /// Get all the GPIO detect events. The returned array could be iterated to e.g. handle each event
+  /// in turn
+  pub fn all() -> [GpioEvent; 8] {
+    [
+      GpioEvent::RisingEdge,
+      GpioEvent::FallingEdge,
+      GpioEvent::BothEdges,
+      GpioEvent::High,
+      GpioEvent::Low,
+      GpioEvent::AsyncRisingEdge,
+      GpioEvent::AsyncFallingEdge,
+      GpioEvent::AsyncBothEdges,
+    ]
+  }
+}
 
-impl core::fmt::Display for GpioError {
+impl core::fmt::Display for GpioEvent {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-    write!(f, "An error occured while accessing the GPIO.")
+    let name = match self {
+      GpioEvent::RisingEdge => "rising edge",
+      GpioEvent::FallingEdge => "falling edge",
+      GpioEvent::BothEdges => "both edges",
+      GpioEvent::High => "high level",
+      GpioEvent::Low => "low level",
+      GpioEvent::AsyncRisingEdge => "async rising edge",
+      GpioEvent::AsyncFallingEdge => "async falling edge",
+      GpioEvent::AsyncBothEdges => "async both edges",
+    };
+    write!(f, "{}", name)
   }
 }
 
-impl core::fmt::Debug for GpioError {
+/// The Raspberry Pi models. The model the crate is build for is choosen with the corresponding
+/// feature while compiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiModel {
+  /// Raspberry Pi 1
+  Pi1,
+  /// Raspberry Pi 2
+  Pi2,
+  /// Raspberry Pi 3, choosen with the ``ruspiro_pi3`` feature
+  Pi3,
+  /// Raspberry Pi 4, choosen with the ``ruspiro_pi4`` feature
+  Pi4,
+  /// Raspberry Pi Zero
+  Zero,
+  /// Raspberry Pi Compute Module 4
+  Cm4,
+}
+
+/// The error type that will be returned on issues with accessing the GPIO peripheral
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpioError {
+  /// The pin is already in use
+  AlreadyInUse(u32),
+  /// The pin is not in use
+  NotInUse(u32),
+  /// The pin is reserved and can not be acquired
+  Reserved(u32),
+  /// The pin number is out of the range of the available GPIO pins
+  OutOfRange(u32),
+  /// The pin is configured for a function that does not support the requested operation
+  InvalidFunction(u32),
+  /// The pull up/down setting has not been taken by the pin
+  PudNotApplied(u32),
+  /// The pin did not reach the expected state in time
+  Timeout(u32),
+  /// The pin does not belong to the same GPIO bank as the other pins requested together
+  BankMismatch(u32),
+  /// The pin is not the one the requested peripheral line is mapped to
+  WrongPeripheralPin(u32),
+  /// The period in micro seconds is too short for the requested signal
+  InvalidPeriod(u32),
+}
+
+impl core::fmt::Display for GpioError {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-    // debug output the same as display
-    <GpioError as core::fmt::Display>::fmt(self, f)
+    match self {
+      GpioError::AlreadyInUse(num) => write!(f, "GPIO {} is already in use.", num),
+      GpioError::NotInUse(num) => write!(f, "GPIO {} is not in use.", num),
+      GpioError::Reserved(num) => write!(f, "GPIO {} is reserved.", num),
+      GpioError::OutOfRange(num) => write!(f, "GPIO {} is out of range.", num),
+      GpioError::InvalidFunction(num) => {
+        write!(f, "GPIO {} is configured for an invalid function.", num)
+      }
+      GpioError::PudNotApplied(num) => {
+        write!(f, "GPIO {} did not take the pull up/down setting.", num)
+      }
+      GpioError::Timeout(num) => write!(f, "GPIO {} timed out.", num),
+      GpioError::BankMismatch(num) => {
+        write!(f, "GPIO {} belongs to a different GPIO bank.", num)
+      }
+      GpioError::WrongPeripheralPin(num) => {
+        write!(f, "GPIO {} is not mapped to the peripheral line.", num)
+      }
+      GpioError::InvalidPeriod(period) => {
+        write!(f, "The period of {}µs is too short.", period)
+      }
+    }
   }
 }
 
+/// bit mask of the pins dropped but not yet released in the [Gpio]
+static DROPPED_PINS: AtomicU64 = AtomicU64::new(0);
+
+/// bit mask of the pins owned by a live handle, see [claim_pin]
+static CLAIMED_PINS: AtomicU64 = AtomicU64::new(0);
+
+/// whether the interrupt of the GPIO bank 0 and 1 has been activated
+static BANK_IRQ_ACTIVE: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+/// whether the GPIO bank interrupts are currently suspended by [Gpio::with_interrupts_disabled]
+static BANK_IRQ_SUSPENDED: AtomicBool = AtomicBool::new(false);
+/// whether the interrupt of the GPIO bank 0 and 1 is held back by the event coalescing
+static BANK_IRQ_HELD: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
 /// recurring/multi call interrupt handler for GPIO 0-31 at bank 0
-static mut BANK0_HANDLER_MC: [Option<Box<dyn FnMut() + 'static + Send>>; 32] = [
+#[cfg(feature = "alloc")]
+static mut BANK0_HANDLER_MC: [Option<Box<dyn RecurringEventHandler>>; 32] = [
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
@@ -301,7 +1513,8 @@ static mut BANK0_HANDLER_MC: [Option<Box<dyn FnMut() + 'static + Send>>; 32] = [
 ];
 
 /// oneshot/single call interrupt handler for GPIO 0-31 at bank 0
-static mut BANK0_HANDLER_SC: [Option<Box<dyn FnOnce() + 'static + Send>>; 32] = [
+#[cfg(feature = "alloc")]
+static mut BANK0_HANDLER_SC: [Option<Box<dyn OneshotEventHandler>>; 32] = [
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
@@ -309,70 +1522,501 @@ static mut BANK0_HANDLER_SC: [Option<Box<dyn FnOnce() + 'static + Send>>; 32] =
 ];
 
 /// recurring/multi callinterrupt handler for GPIO 32-53 at bank 1
-static mut BANK1_HANDLER_MC: [Option<Box<dyn FnMut() + 'static + Send>>; 22] = [
+#[cfg(feature = "alloc")]
+static mut BANK1_HANDLER_MC: [Option<Box<dyn RecurringEventHandler>>; 22] = [
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
   None, None
 ];
 /// oneshot/single call interrupt handler for GPIO 32-53 at bank 1
-static mut BANK1_HANDLER_SC: [Option<Box<dyn FnOnce() + 'static + Send>>; 22] = [
+#[cfg(feature = "alloc")]
+static mut BANK1_HANDLER_SC: [Option<Box<dyn OneshotEventHandler>>; 22] = [
   None, None, None, None, None, None, None, None, None, None,
   None, None, None, None, None, None, None, None, None, None,
   None, None
 ];
 
-/// Implement interrupt handler for GPIO driven interrupts from bank 0 (GPIO 0..31)
+/// The index of the event handler storage of its bank a pin's event handler is stored at. This is
+/// the bit position of the pin in the registers of its bank, so GPIO 47 uses the slot 15 of bank 1.
+/// The registration and the interrupt handler both use this to stay in sync.
+#[cfg(feature = "alloc")]
+const fn handler_slot(num: u32) -> usize {
+  (num & 31) as usize
+}
+
+/// Process the events detected at the given GPIO bank. The events are acknowledged and the
+/// registered event handlers of the triggering pins are called. The bank interrupt handlers of this
+/// crate are thin wrappers around this function. It is exposed for integrations that route the
+/// interrupts through their own vector table and can't use the ``IrqHandler`` attribute.
+///
+/// The triggering pins are processed in ascending order. For each pin the oneshot event handler is
+/// taken and called first, followed by the recurring event handler which stays in place. Registering
+/// an event handler currently removes the handler of the other kind for the same pin, but this
+/// order is guaranteed should both be present.
 /// # Safety
-/// As this handler is only called once at a time for the GPIO bank 0 we can safely access the
-/// static handler array. The only second place is from within the [Gpio] ``Singleton`` accessor, that when
-/// accessed has the interrupts disabled.
-#[IrqHandler(GpioBank0)]
-unsafe fn handle_gpio_bank0(tx: Option<IsrSender<Box<dyn Any>>>) {
+/// The function accesses the static event handler storage of the bank. It is only safe to call it
+/// from within the interrupt context of the GPIO bank interrupt, while the [Gpio] ``Singleton`` is
+/// not locked, and never concurrently for the same bank.
+#[cfg(feature = "alloc")]
+pub unsafe fn process_bank_interrupt(bank: GpioBank) {
   // get the events that raised this interrupt
-  let mut trigger_gpios = get_detected_events(GpioBank::Bank0);
+  let trigger_gpios = get_detected_events(bank);
   // acknowledge all the events triggered
-  acknowledge_detected_events(trigger_gpios, GpioBank::Bank0);
+  acknowledge_detected_events(trigger_gpios, bank);
 
   // for each triggered GPIO pin call the registered handler if any
-  let mut pin = 0;
-  while trigger_gpios != 0 {
+  for pin in BankPins::new(trigger_gpios, bank) {
+    #[cfg(feature = "trace-events")]
+    trace::record(pin, bank);
+    dispatch_event(pin);
+  }
+  #[cfg(feature = "event-coalescing")]
+  coalesce::coalesce_events(bank);
+}
+
+/// Call the event handlers registered for the pin, the oneshot handler first
+/// # Safety
+/// The function accesses the static event handler storage, see [process_bank_interrupt].
+#[cfg(feature = "alloc")]
+unsafe fn dispatch_event(num: u32) {
+  let slot = handler_slot(num);
+  let handlers = match pin_bank(num) {
+    GpioBank::Bank0 => BANK0_HANDLER_SC
+      .get_mut(slot)
+      .zip(BANK0_HANDLER_MC.get_mut(slot)),
+    GpioBank::Bank1 => BANK1_HANDLER_SC
+      .get_mut(slot)
+      .zip(BANK1_HANDLER_MC.get_mut(slot)),
+  };
+  if let Some((single_call, multi_call)) = handlers {
     // take the single call handler if any and call it once
-    if let Some(function) = BANK0_HANDLER_SC[pin].take() {
+    if let Some(function) = single_call.take() {
       (function)()
     };
     // if multi call handler is set call it, leaving the handler in place
-    if let Some(ref mut function) = &mut BANK0_HANDLER_MC[pin] {
+    if let Some(function) = multi_call {
       (function)()
     };
-    trigger_gpios >>= 1;
-    pin += 1;
   }
 }
 
+/// Store the recurring event handler of a pin at the slot [dispatch_event] looks it up. Setting
+/// the recurring handler clears the oneshot handler of this pin.
+/// # Safety
+/// The function accesses the static event handler storage, see [process_bank_interrupt].
+#[cfg(feature = "alloc")]
+unsafe fn store_recurring_handler(num: u32, handler: Box<dyn RecurringEventHandler>) {
+  let slot = handler_slot(num);
+  match pin_bank(num) {
+    GpioBank::Bank0 => {
+      BANK0_HANDLER_MC[slot].replace(handler);
+      let _ = BANK0_HANDLER_SC[slot].take();
+    }
+    GpioBank::Bank1 => {
+      BANK1_HANDLER_MC[slot].replace(handler);
+      let _ = BANK1_HANDLER_SC[slot].take();
+    }
+  }
+}
+
+/// Store the oneshot event handler of a pin at the slot [dispatch_event] looks it up. Setting the
+/// oneshot handler clears the recurring handler of this pin.
+/// # Safety
+/// The function accesses the static event handler storage, see [process_bank_interrupt].
+#[cfg(feature = "alloc")]
+unsafe fn store_oneshot_handler(num: u32, handler: Box<dyn OneshotEventHandler>) {
+  let slot = handler_slot(num);
+  match pin_bank(num) {
+    GpioBank::Bank0 => {
+      BANK0_HANDLER_SC[slot].replace(handler);
+      let _ = BANK0_HANDLER_MC[slot].take();
+    }
+    GpioBank::Bank1 => {
+      BANK1_HANDLER_SC[slot].replace(handler);
+      let _ = BANK1_HANDLER_MC[slot].take();
+    }
+  }
+}
+
+/// Implement interrupt handler for GPIO driven interrupts from bank 0 (GPIO 0..31)
+/// # Safety
+/// As this handler is only called once at a time for the GPIO bank 0 we can safely access the
+/// static handler array. The only second place is from within the [Gpio] ``Singleton`` accessor, that when
+/// accessed has the interrupts disabled.
+#[cfg(feature = "alloc")]
+#[IrqHandler(GpioBank0)]
+unsafe fn handle_gpio_bank0(tx: Option<IsrSender<Box<dyn Any>>>) {
+  process_bank_interrupt(GpioBank::Bank0);
+}
+
 /// Implement interrupt handler for GPIO driven interrupts from bank 1 (GPIO 32..53)
 /// # Safety
 /// As this handler is only called once at a time for the GPIO bank 1 we can safely access the
 /// static handler array. The only second place is from within the [Gpio] ``Singleton`` accessor, that when
 /// accessed has the interrupts disabled.
+#[cfg(feature = "alloc")]
 #[IrqHandler(GpioBank1)]
 unsafe fn handle_gpio_bank1(tx: Option<IsrSender<Box<dyn Any>>>) {
-  // get the events that raised this interrupt
-  let mut trigger_gpios = get_detected_events(GpioBank::Bank1);
-  // acknowledge all the events triggered
-  acknowledge_detected_events(trigger_gpios, GpioBank::Bank1);
+  process_bank_interrupt(GpioBank::Bank1);
+}
 
-  // for each triggered GPIO pin call the registered handler if any
-  let mut pin = 0;
-  while trigger_gpios != 0 {
-    // take the single call handler if any and call it once
-    if let Some(function) = BANK1_HANDLER_SC[pin].take() {
-      (function)()
-    };
-    // if multi call handler is set call it, leaving the handler in place
-    if let Some(ref mut function) = &mut BANK1_HANDLER_MC[pin] {
-      (function)()
+/// Lock serializing the tests that depend on the global state of the crate
+#[cfg(test)]
+static TEST_LOCK: AtomicBool = AtomicBool::new(false);
+
+/// Serialize the tests that depend on the global state of the crate, like the claimed pins, the registered event
+/// handlers or the mocked registers. The mocked registers are reset once the test holds the lock, which is released
+/// when the returned guard is dropped.
+#[cfg(test)]
+pub(crate) fn exclusive_test() -> ExclusiveTest {
+  while TEST_LOCK
+    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+    .is_err()
+  {
+    std::thread::yield_now();
+  }
+  #[cfg(feature = "mock-mmio")]
+  mock::reset();
+  ExclusiveTest
+}
+
+#[cfg(test)]
+pub(crate) struct ExclusiveTest;
+
+#[cfg(test)]
+impl Drop for ExclusiveTest {
+  fn drop(&mut self) {
+    TEST_LOCK.store(false, Ordering::Release);
+  }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+  use super::*;
+
+  /// bit mask of the pins whose event handler has been called
+  static FIRED: AtomicU64 = AtomicU64::new(0);
+
+  #[test]
+  fn dispatch_calls_the_handler_registered_for_the_pin() {
+    let _test = exclusive_test();
+    let pins = [0, 5, 31, 32, 33, 47, 53];
+    unsafe {
+      for &num in pins.iter() {
+        store_recurring_handler(
+          num,
+          Box::new(move || {
+            FIRED.fetch_or(1 << num, Ordering::AcqRel);
+          }),
+        );
+      }
+      for &num in pins.iter() {
+        FIRED.store(0, Ordering::Release);
+        dispatch_event(num);
+        assert_eq!(FIRED.load(Ordering::Acquire), 1 << num, "GPIO {}", num);
+      }
+      // a pin without handler in the same slot of the other bank does not fire
+      FIRED.store(0, Ordering::Release);
+      dispatch_event(37);
+      assert_eq!(FIRED.load(Ordering::Acquire), 0);
+
+      // a oneshot handler replaces the recurring one and fires only once
+      store_oneshot_handler(
+        47,
+        Box::new(|| {
+          FIRED.fetch_or(1 << 63, Ordering::AcqRel);
+        }),
+      );
+      dispatch_event(47);
+      dispatch_event(47);
+      assert_eq!(FIRED.load(Ordering::Acquire), 1 << 63);
+
+      BANK0_HANDLER_MC
+        .iter_mut()
+        .for_each(|handler| *handler = None);
+      BANK1_HANDLER_MC
+        .iter_mut()
+        .for_each(|handler| *handler = None);
+    }
+  }
+
+  #[test]
+  #[cfg(all(feature = "mock-mmio", not(feature = "ruspiro_pi4")))]
+  fn configure_pull_clocks_all_pins_in_one_cycle() {
+    let _test = exclusive_test();
+    let (gppud, gppudclk0, gppudclk1) = (GPIO_BASE + 0x94, GPIO_BASE + 0x98, GPIO_BASE + 0x9C);
+    Gpio::new()
+      .configure_pull(&[4, 5, 40, 53], Pud::PullUp)
+      .unwrap();
+    assert_eq!(mock::writes_to(gppud), [0b10, 0]);
+    assert_eq!(mock::writes_to(gppudclk0), [(1 << 4) | (1 << 5), 0]);
+    assert_eq!(mock::writes_to(gppudclk1), [(1 << 8) | (1 << 21), 0]);
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn configure_pull_does_not_touch_any_pin_for_an_invalid_one() {
+    let _test = exclusive_test();
+    assert_eq!(
+      Gpio::new().configure_pull(&[4, 54], Pud::PullUp),
+      Err(GpioError::OutOfRange(54))
+    );
+    assert!(mock::writes().is_empty());
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn bound_output_follows_the_input_edges() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let input = gpio.get_pin(17).unwrap().into_input();
+    let output = gpio.get_pin(27).unwrap().into_output();
+    gpio.bind_input_to_output(&input, &output).unwrap();
+    assert_eq!(mock::pin_history(27), [false]);
+    unsafe {
+      mock::trigger_event(17, GpioEvent::RisingEdge);
+      assert_eq!(mock::pin_history(27), [false, true]);
+      mock::trigger_event(17, GpioEvent::FallingEdge);
+      assert_eq!(mock::pin_history(27), [false, true, false]);
+      gpio.unbind(&input).unwrap();
+      mock::trigger_event(17, GpioEvent::RisingEdge);
+    }
+    assert_eq!(mock::pin_history(27), [false, true, false]);
+  }
+
+  #[test]
+  #[cfg(all(feature = "mock-mmio", feature = "ruspiro_pi4"))]
+  fn verified_pull_retries_the_pins_that_did_not_take_it() {
+    let _test = exclusive_test();
+    let pup_pdn_cntrl_reg0 = GPIO_BASE + 0xE4;
+    // the first write for pin 2 is lost, pin 3 takes the setting right away
+    mock::drop_writes(pup_pdn_cntrl_reg0, 1);
+    assert_eq!(
+      Gpio::new().configure_pull_verified(&[2, 3], Pud::PullUp, 3),
+      Ok(())
+    );
+    assert_eq!(mock::writes_to(pup_pdn_cntrl_reg0).len(), 3);
+    assert_eq!(mock::peek(pup_pdn_cntrl_reg0), (0b01 << 4) | (0b01 << 6));
+  }
+
+  #[test]
+  #[cfg(all(feature = "mock-mmio", feature = "ruspiro_pi4"))]
+  fn verified_pull_fails_once_the_retries_are_exhausted() {
+    let _test = exclusive_test();
+    let pup_pdn_cntrl_reg0 = GPIO_BASE + 0xE4;
+    mock::drop_writes(pup_pdn_cntrl_reg0, 10);
+    assert_eq!(
+      Gpio::new().configure_pull_verified(&[2], Pud::PullDown, 2),
+      Err(GpioError::PudNotApplied(2))
+    );
+    // the initial attempt and two retries
+    assert_eq!(mock::writes_to(pup_pdn_cntrl_reg0).len(), 3);
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn pin_function_reads_back_the_function_select() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let _pin = gpio.get_pin(21).unwrap().into_alt_f3();
+    assert_eq!(gpio.pin_function(21), Ok(Function::Alt3));
+    assert_eq!(gpio.pin_function(20), Ok(Function::Input));
+    assert_eq!(gpio.pin_function(54), Err(GpioError::OutOfRange(54)));
+    // the UART0 pins as left by the boot firmware
+    mock::poke(GPIO_BASE + 0x04, (0b100 << 12) | (0b100 << 15));
+    assert_eq!(gpio.pin_function(14), Ok(Function::Alt0));
+    assert_eq!(gpio.pin_function(15), Ok(Function::Alt0));
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn with_pin_resets_the_pin_dropped_in_the_closure() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    gpio.with_pin(17, |pin| pin.into_output().high()).unwrap();
+    assert_eq!(get_function(17), Function::Input);
+    assert!(!gpio.is_pin_used(17));
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn with_pin_keeps_the_state_of_an_escaped_pin() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let led = gpio.with_pin(17, |pin| pin.into_output()).unwrap();
+    assert_eq!(get_function(17), Function::Output);
+    assert!(gpio.is_pin_used(17));
+    assert_eq!(gpio.get_pin(17).err(), Some(GpioError::AlreadyInUse(17)));
+    led.high();
+    assert_eq!(mock::pin_history(17), [true]);
+    drop(led);
+    assert!(gpio.get_pin(17).is_ok());
+  }
+
+  #[test]
+  fn pin_numbers_beyond_gpio_53_are_out_of_range() {
+    let _test = exclusive_test();
+    assert_eq!(validate_pin(53), Ok(()));
+    assert_eq!(validate_pin(54), Err(GpioError::OutOfRange(54)));
+    assert_eq!(validate_pin(100), Err(GpioError::OutOfRange(100)));
+    let mut gpio = Gpio::new();
+    assert!(gpio.get_pin(53).is_ok());
+    assert_eq!(gpio.get_pin(54).err(), Some(GpioError::OutOfRange(54)));
+    assert_eq!(gpio.get_pin(100).err(), Some(GpioError::OutOfRange(100)));
+    assert_eq!(gpio.free_pin(54), Err(GpioError::OutOfRange(54)));
+    assert_eq!(gpio.free_pin(100), Err(GpioError::OutOfRange(100)));
+  }
+
+  /// Forge an input pin handle with a pin number the [Gpio] never hands out
+  #[cfg(feature = "mock-mmio")]
+  fn forged_input(num: u32) -> Pin<function::Input, pud::Unknown> {
+    let mut pin = Pin::<function::Unknown, pud::Unknown>::new(53).into_input();
+    pin.num = num;
+    pin
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn event_handler_of_gpio_60_is_rejected() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let error = Some(GpioError::OutOfRange(60));
+    assert_eq!(gpio.get_pin(60).err(), error);
+    let pin = forged_input(60);
+    let writes = mock::writes().len();
+    let recurring = gpio.register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || ());
+    assert_eq!(recurring.err(), error);
+    let with_pin = gpio.register_recurring_event_handler_with_pin(&pin, GpioEvent::High, |_| ());
+    assert_eq!(with_pin.err(), error);
+    let oneshot = gpio.register_oneshot_event_handler(&pin, GpioEvent::FallingEdge, || ());
+    assert_eq!(oneshot.err(), error);
+    assert_eq!(gpio.remove_event_handler(&pin).err(), error);
+    // no register has been touched for the pin
+    assert_eq!(mock::writes().len(), writes);
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn every_entry_point_accepts_gpio_53_and_rejects_gpio_54() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let error = Some(GpioError::OutOfRange(54));
+    let pin = gpio.get_pin(53).ok().unwrap().into_input();
+    assert_eq!(gpio.get_pin(54).err(), error);
+    assert!(gpio.is_pin_used(53));
+    assert!(!gpio.is_pin_used(54));
+    assert_eq!(gpio.pin_function(53), Ok(Function::Input));
+    assert_eq!(gpio.pin_function(54).err(), error);
+    assert!(gpio.pin_pud(53).is_ok());
+    assert_eq!(gpio.pin_pud(54).err(), error);
+    assert_eq!(gpio.configure_pull(&[53], Pud::PullUp), Ok(()));
+    assert_eq!(gpio.configure_pull(&[54], Pud::PullUp).err(), error);
+
+    let forged = forged_input(54);
+    let recurring = gpio.register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || ());
+    assert!(recurring.is_ok());
+    let recurring = gpio.register_recurring_event_handler(&forged, GpioEvent::RisingEdge, || ());
+    assert_eq!(recurring.err(), error);
+    assert_eq!(gpio.activate_detect_events(&pin, GpioEvent::High), Ok(()));
+    let activated = gpio.activate_detect_events(&forged, GpioEvent::High);
+    assert_eq!(activated.err(), error);
+    let oneshot = gpio.register_oneshot_event_handler(&pin, GpioEvent::Low, || ());
+    assert_eq!(oneshot, Ok(()));
+    let oneshot = gpio.register_oneshot_event_handler(&forged, GpioEvent::Low, || ());
+    assert_eq!(oneshot.err(), error);
+    assert_eq!(gpio.remove_event_handler(&pin), Ok(()));
+    assert_eq!(gpio.remove_event_handler(&forged).err(), error);
+    drop(pin);
+
+    assert_eq!(gpio.mark_reserved(53), Ok(()));
+    gpio.unmark_reserved(53);
+    assert_eq!(gpio.mark_reserved(54).err(), error);
+    let num = gpio.get_pin(53).ok().unwrap().into_num();
+    assert_eq!(gpio.free_pin_keep_state(num), Ok(()));
+    assert_eq!(gpio.free_pin_keep_state(54).err(), error);
+    let num = gpio.get_pin(53).ok().unwrap().into_num();
+    assert_eq!(gpio.free_pin(num), Ok(()));
+    assert_eq!(gpio.free_pin(54).err(), error);
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn scoped_event_handler_is_gone_after_the_scope() {
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let pin = gpio.get_pin(17).unwrap().into_input();
+    let count = || {
+      CALLS.fetch_add(1, Ordering::AcqRel);
     };
-    trigger_gpios >>= 1;
-    pin += 1;
+    let result = gpio.with_event(&pin, GpioEvent::RisingEdge, count, || {
+      unsafe { mock::trigger_event(17, GpioEvent::RisingEdge) };
+      // an event detected while the interrupts are disabled stays pending
+      mock::poke(GPIO_BASE + 0x40, 1 << 17);
+      42
+    });
+    assert_eq!(result, Ok(42));
+    assert_eq!(CALLS.load(Ordering::Acquire), 1);
+    assert_eq!(get_detect_enables(GpioBank::Bank0), [0; 6]);
+    assert_eq!(mock::peek(GPIO_BASE + 0x40), 0);
+    unsafe {
+      assert!(BANK0_HANDLER_MC[17].is_none());
+      mock::set_level(17, false);
+      mock::trigger_event(17, GpioEvent::RisingEdge);
+    }
+    assert_eq!(CALLS.load(Ordering::Acquire), 1);
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn scoped_event_handler_is_gone_after_the_body_panicked() {
+    let _test = exclusive_test();
+    let mut gpio = Gpio::new();
+    let pin = gpio.get_pin(17).unwrap().into_input();
+    let body = || panic!("body failed");
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      gpio.with_event(&pin, GpioEvent::FallingEdge, || (), body)
+    }));
+    assert!(result.is_err());
+    assert_eq!(get_detect_enables(GpioBank::Bank0), [0; 6]);
+    unsafe { assert!(BANK0_HANDLER_MC[17].is_none()) };
+  }
+
+  #[test]
+  fn registering_an_event_handler_removes_the_other_kind() {
+    let _test = exclusive_test();
+    unsafe {
+      store_recurring_handler(40, Box::new(|| ()));
+      store_oneshot_handler(40, Box::new(|| ()));
+      assert!(BANK1_HANDLER_SC[8].is_some());
+      assert!(BANK1_HANDLER_MC[8].is_none());
+      store_recurring_handler(40, Box::new(|| ()));
+      assert!(BANK1_HANDLER_MC[8].is_some());
+      assert!(BANK1_HANDLER_SC[8].is_none());
+      BANK1_HANDLER_MC[8] = None;
+    }
+  }
+
+  #[test]
+  fn oneshot_event_handler_is_dispatched_before_the_recurring_one() {
+    static ORDER: AtomicU64 = AtomicU64::new(0);
+    let _test = exclusive_test();
+    unsafe {
+      // both kinds can't be registered through the public API, place them directly
+      BANK0_HANDLER_MC[3] = Some(Box::new(|| {
+        let order = ORDER.load(Ordering::Acquire);
+        ORDER.store((order << 4) | 2, Ordering::Release);
+      }));
+      BANK0_HANDLER_SC[3] = Some(Box::new(|| {
+        let order = ORDER.load(Ordering::Acquire);
+        ORDER.store((order << 4) | 1, Ordering::Release);
+      }));
+      dispatch_event(3);
+      assert_eq!(ORDER.load(Ordering::Acquire), 0x12);
+      // the oneshot handler is gone, the recurring one stays in place
+      dispatch_event(3);
+      assert_eq!(ORDER.load(Ordering::Acquire), 0x122);
+      BANK0_HANDLER_MC[3] = None;
+    }
   }
 }
@@ -0,0 +1,525 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2019 by the authors
+ *
+ * Author: André Borrmann
+ * License: Appache License 2.0
+ **********************************************************************************************************************/
+
+//! # Mocked GPIO registers
+//!
+//! With the ``mock-mmio`` feature the MMIO registers of the GPIO, the pads and the system timer are backed by a static
+//! array instead of the peripheral addresses. This allows to run the pin logic on the development host, e.g. in unit
+//! tests, and to check the values written to the registers. The registers are addressed with their peripheral address
+//! as given in the datasheet of the chosen model. The mocked registers mimic the parts of the hardware the pin logic
+//! relies on:
+//! - writing to the GPSET/GPCLR registers changes the level of the pins configured as output
+//! - the GPEDS registers are cleared by writing a 1 to the bits to acknowledge
+//!
+//! The external signals are simulated with ``set_level`` for the pin level.
+//!
+//! **HINT*: The mocked registers are shared by the whole process. Tests using them should not run in parallel or
+//! call ``reset`` before they start.
+//!
+//! # Example
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! # fn doc() {
+//! let mut gpio = Gpio::new();
+//! gpio.get_pin(17).unwrap().into_output().high();
+//! assert_eq!(mock::peek(mock::GPIO_BASE + 0x1C), 1 << 17);
+//! # }
+//! ```
+
+use crate::interface::{self, BankPins, Function, GpioBank};
+use crate::GpioEvent;
+use alloc::{boxed::Box, vec::Vec};
+use core::any::Any;
+use core::sync::atomic::{AtomicU32, Ordering};
+use ruspiro_interrupt::{Interrupt, IsrSender};
+use ruspiro_singleton::Singleton;
+
+/// Base address of the GPIO registers of the chosen model
+pub const GPIO_BASE: usize = interface::GPIO_BASE;
+
+/// Number of mocked registers per peripheral, this covers the first 256 bytes of its MMIO page
+const BLOCK_SIZE: usize = 64;
+
+/// The peripherals with mocked registers, the GPIO, the pads control and the system timer
+const BLOCKS: [usize; 3] = [
+  interface::GPIO_BASE,
+  interface::PADS_BASE,
+  interface::SYS_TIMER_BASE,
+];
+
+/// Offsets of the GPIO registers with a special behavior of the mock
+const GPSET_OFFSET: [usize; 2] = [0x1C, 0x20];
+const GPCLR_OFFSET: [usize; 2] = [0x28, 0x2C];
+const GPLEV_OFFSET: [usize; 2] = [0x34, 0x38];
+const GPEDS_OFFSET: [usize; 2] = [0x40, 0x44];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU32 = AtomicU32::new(0);
+
+/// The storage of the mocked registers
+static REGISTERS: [AtomicU32; 3 * BLOCK_SIZE] = [ZERO; 3 * BLOCK_SIZE];
+
+/// Log of all writes to the mocked registers in the order they have been issued
+static WRITES: Singleton<Vec<(usize, u32)>> = Singleton::new(Vec::new());
+
+/// Number of the next writes to a register that do not take effect, see ``drop_writes``
+static DROPPED_WRITES: Singleton<Vec<(usize, u32)>> = Singleton::new(Vec::new());
+
+/// The interrupts activated through the mocked interrupt manager, bit 0 is the GPIO bank 0, bit 1 the GPIO bank 1,
+/// bit 2 the system timer 1 and bit 3 the system timer 3
+static ACTIVE_IRQS: AtomicU32 = AtomicU32::new(0);
+
+/// Read the current value of the mocked register at the given address. This does not have any side effect.
+///
+/// # Panics
+/// If there is no mocked register at this address
+pub fn peek(addr: usize) -> u32 {
+  register(addr).load(Ordering::Acquire)
+}
+
+/// Store a value in the mocked register at the given address. Other than a write through the register accessors this
+/// does not have any side effect and is not logged, so it could be used to set up the state of the hardware like the
+/// pending event detect status.
+///
+/// # Panics
+/// If there is no mocked register at this address
+pub fn poke(addr: usize, value: u32) {
+  register(addr).store(value, Ordering::Release);
+}
+
+/// Simulate an external signal at the given pin by setting its level in the GPLEV register
+pub fn set_level(num: u32, level: bool) {
+  let (bank, mask) = bank_mask(num);
+  let lev = register(GPIO_BASE + GPLEV_OFFSET[bank]);
+  if level {
+    lev.fetch_or(mask, Ordering::AcqRel);
+  } else {
+    lev.fetch_and(!mask, Ordering::AcqRel);
+  }
+}
+
+/// Get all writes to the mocked registers as pairs of address and value in the order they have been issued
+pub fn writes() -> Vec<(usize, u32)> {
+  WRITES.with_mut(|writes| writes.clone())
+}
+
+/// Get the values written to the mocked register at the given address in the order they have been issued
+pub fn writes_to(addr: usize) -> Vec<u32> {
+  WRITES.with_mut(|writes| {
+    writes
+      .iter()
+      .filter(|(target, _)| *target == addr)
+      .map(|(_, value)| *value)
+      .collect()
+  })
+}
+
+/// Get the sequence of levels written to the given pin, ``true`` for each write to its GPSET register and ``false``
+/// for each write to its GPCLR register. This allows to verify a driver pulsed a pin and not only the final level.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// let mut gpio = Gpio::new();
+/// let pin = gpio.get_pin(17).unwrap().into_output();
+/// pin.high();
+/// pin.low();
+/// assert_eq!(mock::pin_history(17), [true, false]);
+/// # }
+/// ```
+pub fn pin_history(num: u32) -> Vec<bool> {
+  let (bank, mask) = bank_mask(num);
+  let set = GPIO_BASE + GPSET_OFFSET[bank];
+  let clear = GPIO_BASE + GPCLR_OFFSET[bank];
+  WRITES.with_mut(|writes| {
+    writes
+      .iter()
+      .filter(|(_, value)| value & mask != 0)
+      .filter_map(|&(addr, _)| match addr {
+        _ if addr == set => Some(true),
+        _ if addr == clear => Some(false),
+        _ => None,
+      })
+      .collect()
+  })
+}
+
+/// Simulate the given event at a pin. The level of the pin changes to the level the event leaves it at, the both edges
+/// events toggle the level. If the detection of the event is armed for the pin its event detect status is set and, if
+/// the interrupt of its bank is active, the bank interrupt is processed so the registered event handler is called.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// let mut gpio = Gpio::new();
+/// let pin = gpio.get_pin(40).unwrap().into_input();
+/// gpio
+///   .register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || println!("GPIO 40 rising"))
+///   .unwrap();
+/// unsafe { mock::trigger_event(40, GpioEvent::RisingEdge) };
+/// # }
+/// ```
+/// # Safety
+/// The event handlers are called the same way as from the bank interrupt, see [crate::process_bank_interrupt]. This
+/// must not be called while the [crate::GPIO] ``Singleton`` is locked.
+pub unsafe fn trigger_event(num: u32, event: GpioEvent) {
+  let (bank, mask) = bank_mask(num);
+  let level = match event {
+    GpioEvent::RisingEdge | GpioEvent::AsyncRisingEdge | GpioEvent::High => true,
+    GpioEvent::FallingEdge | GpioEvent::AsyncFallingEdge | GpioEvent::Low => false,
+    GpioEvent::BothEdges | GpioEvent::AsyncBothEdges => {
+      peek(GPIO_BASE + GPLEV_OFFSET[bank]) & mask == 0
+    }
+  };
+  // the index of the detect enable register in the order of ``get_detect_enables``
+  let detector = match (event, level) {
+    (GpioEvent::RisingEdge, _) | (GpioEvent::BothEdges, true) => 0,
+    (GpioEvent::FallingEdge, _) | (GpioEvent::BothEdges, false) => 1,
+    (GpioEvent::High, _) => 2,
+    (GpioEvent::Low, _) => 3,
+    (GpioEvent::AsyncRisingEdge, _) | (GpioEvent::AsyncBothEdges, true) => 4,
+    (GpioEvent::AsyncFallingEdge, _) | (GpioEvent::AsyncBothEdges, false) => 5,
+  };
+  set_level(num, level);
+  if interface::get_detect_enables(gpio_bank(bank))[detector] & mask != 0 {
+    register(GPIO_BASE + GPEDS_OFFSET[bank]).fetch_or(mask, Ordering::AcqRel);
+    let irq = if bank == 0 {
+      Interrupt::GpioBank0
+    } else {
+      Interrupt::GpioBank1
+    };
+    if is_irq_active(irq) {
+      crate::process_bank_interrupt(gpio_bank(bank));
+    }
+  }
+}
+
+/// Let the given number of the next writes to the mocked register at the given address not take effect. The writes are
+/// still logged. This simulates a register write that does not "take" on the hardware, e.g. to test a retry.
+pub fn drop_writes(addr: usize, count: u32) {
+  DROPPED_WRITES.with_mut(|dropped| {
+    dropped.retain(|&(target, _)| target != addr);
+    dropped.push((addr, count));
+  });
+}
+
+/// Returns whether the interrupt has been activated and not yet deactivated again
+pub fn is_irq_active(irq: Interrupt) -> bool {
+  irq_bit(irq) & ACTIVE_IRQS.load(Ordering::Acquire) != 0
+}
+
+/// Reset all mocked registers to 0, clear the log of the writes and deactivate all interrupts
+pub fn reset() {
+  for register in REGISTERS.iter() {
+    register.store(0, Ordering::Release);
+  }
+  WRITES.with_mut(|writes| writes.clear());
+  DROPPED_WRITES.with_mut(|dropped| dropped.clear());
+  ACTIVE_IRQS.store(0, Ordering::Release);
+}
+
+/// Read the mocked register at the given address on behalf of a register accessor
+pub(crate) fn read(addr: usize) -> u32 {
+  peek(addr)
+}
+
+/// Write the mocked register at the given address on behalf of a register accessor. The write is logged and mimics
+/// the behavior of the hardware for the registers the pin logic relies on.
+pub(crate) fn write(addr: usize, value: u32) {
+  WRITES.with_mut(|writes| writes.push((addr, value)));
+  let dropped = DROPPED_WRITES.with_mut(|dropped| {
+    match dropped.iter_mut().find(|(target, _)| *target == addr) {
+      Some((_, count)) if *count > 0 => {
+        *count -= 1;
+        true
+      }
+      _ => false,
+    }
+  });
+  if dropped {
+    return;
+  }
+  let register = register(addr);
+  let offset = addr.wrapping_sub(GPIO_BASE);
+  if GPEDS_OFFSET.contains(&offset) {
+    // the event detect status is acknowledged by writing a 1 to the bits
+    register.fetch_and(!value, Ordering::AcqRel);
+  } else if let Some(bank) = GPSET_OFFSET.iter().position(|&set| set == offset) {
+    register.store(value, Ordering::Release);
+    drive_outputs(bank, value, true);
+  } else if let Some(bank) = GPCLR_OFFSET.iter().position(|&clr| clr == offset) {
+    register.store(value, Ordering::Release);
+    drive_outputs(bank, value, false);
+  } else {
+    register.store(value, Ordering::Release);
+  }
+}
+
+/// Change the level of the pins of the mask that are configured as output, the inputs are driven from outside
+fn drive_outputs(bank: usize, mask: u32, level: bool) {
+  for pin in BankPins::new(mask, gpio_bank(bank)) {
+    if interface::get_function(pin) == Function::Output {
+      set_level(pin, level);
+    }
+  }
+}
+
+fn register(addr: usize) -> &'static AtomicU32 {
+  let offset = addr & 0xFFF;
+  match BLOCKS.iter().position(|&base| base == addr & !0xFFF) {
+    Some(block) if offset < BLOCK_SIZE * 4 => &REGISTERS[block * BLOCK_SIZE + offset / 4],
+    _ => panic!("no mocked register at {:#x}", addr),
+  }
+}
+
+const fn gpio_bank(bank: usize) -> GpioBank {
+  if bank == 0 {
+    GpioBank::Bank0
+  } else {
+    GpioBank::Bank1
+  }
+}
+
+const fn bank_mask(num: u32) -> (usize, u32) {
+  ((num / 32) as usize, 1 << (num & 31))
+}
+
+fn irq_bit(irq: Interrupt) -> u32 {
+  match irq {
+    Interrupt::GpioBank0 => 0b0001,
+    Interrupt::GpioBank1 => 0b0010,
+    Interrupt::SystemTimer1 => 0b0100,
+    Interrupt::SystemTimer3 => 0b1000,
+    _ => 0,
+  }
+}
+
+/// The register accessors backed by the mocked registers. They provide the same API as the ``ruspiro-mmio-register``
+/// crate as far as it is used in this crate.
+pub(crate) mod registers {
+  use core::marker::PhantomData;
+
+  /// A field within a mocked register given by its mask and shift
+  #[derive(Clone, Copy)]
+  pub struct RegisterField<T> {
+    mask: T,
+    shift: T,
+  }
+
+  impl RegisterField<u32> {
+    pub const fn new(mask: u32, shift: u32) -> Self {
+      RegisterField { mask, shift }
+    }
+  }
+
+  /// A mocked register that could be read and written
+  #[derive(Clone, Copy)]
+  pub struct ReadWrite<T> {
+    addr: usize,
+    _type: PhantomData<T>,
+  }
+
+  impl ReadWrite<u32> {
+    pub const fn new(addr: usize) -> Self {
+      ReadWrite {
+        addr,
+        _type: PhantomData,
+      }
+    }
+
+    pub const fn addr(&self) -> usize {
+      self.addr
+    }
+
+    pub fn get(&self) -> u32 {
+      super::read(self.addr)
+    }
+
+    pub fn set(&self, value: u32) {
+      super::write(self.addr, value)
+    }
+
+    pub fn modify(&self, field: RegisterField<u32>, value: u32) {
+      let mask = field.mask << field.shift;
+      self.set((self.get() & !mask) | ((value << field.shift) & mask))
+    }
+  }
+
+  /// A mocked register that could only be read
+  #[derive(Clone, Copy)]
+  pub struct ReadOnly<T> {
+    addr: usize,
+    _type: PhantomData<T>,
+  }
+
+  impl ReadOnly<u32> {
+    pub const fn new(addr: usize) -> Self {
+      ReadOnly {
+        addr,
+        _type: PhantomData,
+      }
+    }
+
+    pub const fn addr(&self) -> usize {
+      self.addr
+    }
+
+    pub fn get(&self) -> u32 {
+      super::read(self.addr)
+    }
+  }
+
+  /// A mocked register that could only be written
+  #[derive(Clone, Copy)]
+  pub struct WriteOnly<T> {
+    addr: usize,
+    _type: PhantomData<T>,
+  }
+
+  impl WriteOnly<u32> {
+    pub const fn new(addr: usize) -> Self {
+      WriteOnly {
+        addr,
+        _type: PhantomData,
+      }
+    }
+
+    pub const fn addr(&self) -> usize {
+      self.addr
+    }
+
+    pub fn set(&self, value: u32) {
+      super::write(self.addr, value)
+    }
+  }
+
+  /// Define mocked registers with the same syntax as the ``define_mmio_register!`` macro of the
+  /// ``ruspiro-mmio-register`` crate
+  macro_rules! define_mmio_register {
+    [$($(#[$doc:meta])* $vis:vis $name:ident<$access:ident<$t:ty>@($addr:expr)>
+      $({ $($field:ident OFFSET($offset:literal) BITS($bits:literal)),* })?),*] => {
+      $(
+        $(#[$doc])*
+        #[allow(non_snake_case, non_upper_case_globals, unused_imports, dead_code)]
+        $vis mod $name {
+          use super::*;
+          pub const Register: $crate::mock::registers::$access<$t> =
+            $crate::mock::registers::$access::<$t>::new($addr);
+          $($(
+            pub const $field: $crate::mock::registers::RegisterField<$t> =
+              $crate::mock::registers::RegisterField::<$t>::new((1 << $bits) - 1, $offset);
+          )*)?
+        }
+      )*
+    };
+  }
+  pub(crate) use define_mmio_register;
+}
+
+/// The interrupt manager used with the mocked registers. It only records the activated interrupts, see
+/// ``is_irq_active``.
+pub(crate) mod irq {
+  use super::*;
+
+  pub(crate) fn activate(irq: Interrupt, _tx: Option<IsrSender<Box<dyn Any>>>) {
+    ACTIVE_IRQS.fetch_or(irq_bit(irq), Ordering::AcqRel);
+  }
+
+  pub(crate) fn deactivate(irq: Interrupt) {
+    ACTIVE_IRQS.fetch_and(!irq_bit(irq), Ordering::AcqRel);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::interface::{set_function, set_pins};
+
+  #[test]
+  fn event_status_is_acknowledged_by_writing_ones() {
+    let _test = crate::exclusive_test();
+    poke(GPIO_BASE + 0x40, 0b1011);
+    write(GPIO_BASE + 0x40, 0b0010);
+    assert_eq!(peek(GPIO_BASE + 0x40), 0b1001);
+  }
+
+  #[test]
+  fn only_outputs_follow_the_set_register() {
+    let _test = crate::exclusive_test();
+    set_function(17, Function::Output);
+    set_function(40, Function::Input);
+    set_pins(GpioBank::Bank0, 1 << 17);
+    set_pins(GpioBank::Bank1, 1 << 8);
+    assert_eq!(peek(GPIO_BASE + 0x34), 1 << 17);
+    assert_eq!(peek(GPIO_BASE + 0x38), 0);
+    assert_eq!(writes_to(GPIO_BASE + 0x1C), [1 << 17]);
+  }
+
+  #[test]
+  fn pin_history_lists_the_levels_written_to_the_pin() {
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    let enable = gpio.get_pin(17).unwrap().into_output();
+    let other = gpio.get_pin(40).unwrap().into_output();
+    enable.high();
+    other.high();
+    enable.low();
+    other.low();
+    enable.high();
+    assert_eq!(pin_history(17), [true, false, true]);
+    assert_eq!(pin_history(40), [true, false]);
+    assert!(pin_history(18).is_empty());
+  }
+
+  #[test]
+  fn triggered_event_calls_the_handler_of_gpio_40() {
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    let pin = gpio.get_pin(40).unwrap().into_input();
+    gpio
+      .register_recurring_event_handler(&pin, GpioEvent::RisingEdge, || {
+        CALLS.fetch_add(1, Ordering::AcqRel);
+      })
+      .unwrap();
+    unsafe {
+      trigger_event(40, GpioEvent::RisingEdge);
+      // the falling edge is not armed and does not call the handler
+      trigger_event(40, GpioEvent::FallingEdge);
+      trigger_event(40, GpioEvent::RisingEdge);
+    }
+    assert_eq!(CALLS.load(Ordering::Acquire), 2);
+    // the interrupt processing acknowledged the events
+    assert_eq!(peek(GPIO_BASE + 0x44), 0);
+    gpio.remove_event_handler(&pin).unwrap();
+  }
+
+  #[test]
+  fn triggered_event_of_another_pin_does_not_call_the_handler() {
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    let pin = gpio.get_pin(40).unwrap().into_input();
+    let other = gpio.get_pin(8).unwrap().into_input();
+    gpio
+      .register_oneshot_event_handler(&pin, GpioEvent::BothEdges, || {
+        CALLS.fetch_add(1, Ordering::AcqRel);
+      })
+      .unwrap();
+    gpio
+      .register_recurring_event_handler(&other, GpioEvent::BothEdges, || ())
+      .unwrap();
+    unsafe {
+      // GPIO 8 uses the same slot in bank 0 as GPIO 40 in bank 1
+      trigger_event(8, GpioEvent::BothEdges);
+      trigger_event(40, GpioEvent::BothEdges);
+      trigger_event(40, GpioEvent::BothEdges);
+    }
+    assert_eq!(CALLS.load(Ordering::Acquire), 1);
+    gpio.remove_event_handler(&other).unwrap();
+  }
+}
@@ -0,0 +1,159 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Multiplexed displays
+//!
+//! Refresh 7-segment or LED matrix displays that share the segment lines between all digits and enable one digit at a
+//! time. The segment and digit lines are expected at the GPIO bank 0 and to be active high, other banks are not
+//! supported.
+//!
+
+use crate::interface::*;
+use crate::time::Delay;
+use crate::{Gpio, GpioError};
+
+impl Gpio {
+  /// Drive a single digit of a multiplexed display. All digits given by the ``digit_mask`` are blanked, the segments
+  /// given by the ``seg_mask`` are driven to the ``segments`` pattern and the ``digit`` lines are enabled. After
+  /// holding the digit for ``hold_us`` micro seconds all digits are blanked again.
+  /// The segment and digit changes are written with a single clear and a single set register write.
+  /// Only pins of the GPIO bank 0 are supported, they are given by bit masks of this bank.
+  /// Returns an Err if any of the segment or digit pins is not in use or not configured as output, in this case no
+  /// pin is driven.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl Delay for Timer { fn delay_us(&self, _: u32) {} }
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     // segments a..g at GPIO 4..10, digits at GPIO 20..23
+  ///     let display = gpio.get_port(GpioBank::Bank0, 0x7F << 4 | 0xF << 20).unwrap();
+  ///     display.enable_output();
+  ///     // show a "1" at the second digit
+  ///     gpio.drive_pattern(0b000_0110 << 4, 0x7F << 4, 1 << 21, 0xF << 20, &Timer, 2_000)
+  /// })
+  /// .unwrap();
+  /// # }
+  /// ```
+  pub fn drive_pattern(
+    &mut self,
+    segments: u32,
+    seg_mask: u32,
+    digit: u32,
+    digit_mask: u32,
+    delay: &impl Delay,
+    hold_us: u32,
+  ) -> Result<(), GpioError> {
+    self.check_outputs(GpioBank::Bank0, seg_mask | digit_mask)?;
+    drive_digit(segments, seg_mask, digit, digit_mask, delay, hold_us);
+    Ok(())
+  }
+
+  /// Refresh all digits of a multiplexed display once. The segment pattern at index ``n`` of the ``frame`` is shown
+  /// with the digit lines at index ``n`` of the ``digits`` enabled. See [Gpio::drive_pattern] for the details of each
+  /// digit. This needs to be called repeatedly to keep the display lit.
+  /// Returns an Err if any of the segment or digit pins is not in use or not configured as output, in this case no
+  /// pin is driven.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl Delay for Timer { fn delay_us(&self, _: u32) {} }
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let display = gpio.get_port(GpioBank::Bank0, 0x7F << 4 | 0xF << 20).unwrap();
+  ///     display.enable_output();
+  ///     let frame = [0b011_1111 << 4, 0b000_0110 << 4, 0b101_1011 << 4, 0b100_1111 << 4];
+  ///     let digits = [1 << 20, 1 << 21, 1 << 22, 1 << 23];
+  ///     gpio.refresh(&frame, 0x7F << 4, &digits, &Timer, 2_000)
+  /// })
+  /// .unwrap();
+  /// # }
+  /// ```
+  pub fn refresh(
+    &mut self,
+    frame: &[u32],
+    seg_mask: u32,
+    digits: &[u32],
+    delay: &impl Delay,
+    hold_us: u32,
+  ) -> Result<(), GpioError> {
+    let digit_mask = digits.iter().fold(0, |mask, digit| mask | digit);
+    self.check_outputs(GpioBank::Bank0, seg_mask | digit_mask)?;
+    for (&segments, &digit) in frame.iter().zip(digits.iter()) {
+      drive_digit(segments, seg_mask, digit, digit_mask, delay, hold_us);
+    }
+    Ok(())
+  }
+}
+
+/// Drive a single digit, see [Gpio::drive_pattern]
+fn drive_digit(
+  segments: u32,
+  seg_mask: u32,
+  digit: u32,
+  digit_mask: u32,
+  delay: &impl Delay,
+  hold_us: u32,
+) {
+  let (clear, set) = digit_masks(segments, seg_mask, digit, digit_mask);
+  // blank all digits and switch off the segments not lit
+  clear_pins(GpioBank::Bank0, clear);
+  // light the segments and enable the digit at once
+  set_pins(GpioBank::Bank0, set);
+  delay.delay_us(hold_us);
+  clear_pins(GpioBank::Bank0, digit_mask);
+}
+
+/// Get the masks of the pins to clear and to set for a single digit. Segment and digit lines outside of their masks
+/// are ignored.
+const fn digit_masks(segments: u32, seg_mask: u32, digit: u32, digit_mask: u32) -> (u32, u32) {
+  let segments = segments & seg_mask;
+  let digit = digit & digit_mask;
+  (digit_mask | (seg_mask & !segments), segments | digit)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SEG_MASK: u32 = 0x7F << 4;
+  const DIGIT_MASK: u32 = 0xF << 20;
+
+  #[test]
+  fn digit_blanks_all_digits_and_unlit_segments() {
+    let (clear, set) = digit_masks(0b000_0110 << 4, SEG_MASK, 1 << 21, DIGIT_MASK);
+    assert_eq!(clear, DIGIT_MASK | 0b111_1001 << 4);
+    assert_eq!(set, 0b000_0110 << 4 | 1 << 21);
+    // the enabled digit is part of both writes, it is enabled as the set is written last
+    assert_eq!(clear & set, 1 << 21);
+  }
+
+  #[test]
+  fn lines_outside_the_masks_are_ignored() {
+    let (clear, set) = digit_masks(0xFFFF_FFFF, SEG_MASK, 1 << 21 | 1 << 2, DIGIT_MASK);
+    assert_eq!(clear, DIGIT_MASK);
+    assert_eq!(set, SEG_MASK | 1 << 21);
+  }
+
+  #[test]
+  fn each_digit_of_a_frame_gets_its_own_pattern() {
+    let frame = [
+      0b011_1111 << 4,
+      0b000_0110 << 4,
+      0b101_1011 << 4,
+      0b100_1111 << 4,
+    ];
+    let digits = [1 << 20, 1 << 21, 1 << 22, 1 << 23];
+    for (&segments, &digit) in frame.iter().zip(digits.iter()) {
+      let (clear, set) = digit_masks(segments, SEG_MASK, digit, DIGIT_MASK);
+      assert_eq!(set & DIGIT_MASK, digit);
+      assert_eq!(set & SEG_MASK, segments);
+      assert_eq!(clear & SEG_MASK, SEG_MASK & !segments);
+    }
+  }
+}
@@ -0,0 +1,137 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Output array
+//!
+//! A fixed set of output pins that represent the bits of a binary value. The pins could be spread across both GPIO
+//! banks, the pins of each bank are driven with a single register write.
+//!
+
+use crate::interface::*;
+use crate::{function, Pin};
+
+/// A set of output pins representing the bits of a value. The first pin is bit ``0`` of the value.
+/// # Example
+/// ```no_run
+/// # use ruspiro_gpio::*;
+/// # fn doc() {
+/// let pins = GPIO.with_mut(|gpio| {
+///     [
+///         gpio.get_pin(5).unwrap().into_output(),
+///         gpio.get_pin(6).unwrap().into_output(),
+///         gpio.get_pin(13).unwrap().into_output(),
+///     ]
+/// });
+/// let array = OutputArray::new(pins);
+/// for position in 0..8 {
+///     // consecutive positions only change a single pin
+///     array.write_gray(position);
+/// }
+/// # }
+/// ```
+pub struct OutputArray<PUD, const N: usize> {
+  pins: [Pin<function::Output, PUD>; N],
+}
+
+impl<PUD, const N: usize> OutputArray<PUD, N> {
+  /// Create the output array from the given pins
+  pub fn new(pins: [Pin<function::Output, PUD>; N]) -> Self {
+    Self { pins }
+  }
+
+  /// Release the pins of the output array
+  pub fn into_inner(self) -> [Pin<function::Output, PUD>; N] {
+    self.pins
+  }
+
+  /// Drive the pins to the binary representation of the value. Bits beyond the number of pins are
+  /// ignored.
+  pub fn write_value(&self, value: u32) {
+    let (set, clear) = bank_masks(self.pins.iter().map(|pin| pin.num), value);
+    for (bit, pin) in self.pins.iter().enumerate() {
+      pin.track_driven(bit_set(value, bit));
+    }
+    for (idx, bank) in [GpioBank::Bank0, GpioBank::Bank1].iter().enumerate() {
+      if set[idx] != 0 {
+        set_pins(*bank, set[idx]);
+      }
+      if clear[idx] != 0 {
+        clear_pins(*bank, clear[idx]);
+      }
+    }
+  }
+
+  /// Drive the pins to the Gray code of the value. Consecutive values differ in a single pin only.
+  pub fn write_gray(&self, value: u32) {
+    self.write_value(gray_code(value));
+  }
+}
+
+/// Whether the bit of the value is set, bits beyond 31 are never set
+fn bit_set(value: u32, bit: usize) -> bool {
+  bit < 32 && value & (1 << bit) != 0
+}
+
+/// The Gray code of the value
+const fn gray_code(value: u32) -> u32 {
+  value ^ (value >> 1)
+}
+
+/// The set and clear masks of both banks to drive the pins to the binary representation of the
+/// value. The first pin is bit ``0`` of the value.
+fn bank_masks(pins: impl Iterator<Item = u32>, value: u32) -> ([u32; 2], [u32; 2]) {
+  let mut set = [0u32; 2];
+  let mut clear = [0u32; 2];
+  for (bit, num) in pins.enumerate() {
+    let bank = (num / 32) as usize;
+    if bit_set(value, bit) {
+      set[bank] |= 1 << (num & 31);
+    } else {
+      clear[bank] |= 1 << (num & 31);
+    }
+  }
+  (set, clear)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn first_pin_is_bit_0() {
+    let (set, clear) = bank_masks([5, 6, 13].iter().copied(), 0b001);
+    assert_eq!(set, [1 << 5, 0]);
+    assert_eq!(clear, [1 << 6 | 1 << 13, 0]);
+    let (set, clear) = bank_masks([5, 6, 13].iter().copied(), 0b110);
+    assert_eq!(set, [1 << 6 | 1 << 13, 0]);
+    assert_eq!(clear, [1 << 5, 0]);
+  }
+
+  #[test]
+  fn pins_are_split_by_bank() {
+    let (set, clear) = bank_masks([4, 40, 31, 32].iter().copied(), 0b0101);
+    assert_eq!(set, [1 << 4 | 1 << 31, 0]);
+    assert_eq!(clear, [0, 1 << 8 | 1 << 0]);
+  }
+
+  #[test]
+  fn bits_beyond_the_pins_are_ignored() {
+    let (set, clear) = bank_masks([2, 3].iter().copied(), 0xFFFF_FFFC);
+    assert_eq!(set, [0, 0]);
+    assert_eq!(clear, [1 << 2 | 1 << 3, 0]);
+  }
+
+  #[test]
+  fn consecutive_gray_codes_differ_in_one_bit() {
+    assert_eq!(
+      (0..8).map(gray_code).collect::<Vec<_>>(),
+      [0b000, 0b001, 0b011, 0b010, 0b110, 0b111, 0b101, 0b100]
+    );
+    for value in 0..1024 {
+      assert_eq!((gray_code(value) ^ gray_code(value + 1)).count_ones(), 1);
+    }
+  }
+}
@@ -0,0 +1,83 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # GPIO pad control
+//!
+//! The drive strength, slew rate and input hysteresis of the GPIO pins are not configured per pin but for three groups
+//! of pins. Changing them for one pin affects all pins of the same group, so reading the current configuration of a
+//! group is the only way to verify it or to avoid clobbering the configuration of other pins.
+//!
+
+use crate::interface::*;
+use crate::Gpio;
+
+/// The GPIO pin groups sharing the same pad configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PadGroup {
+  /// GPIO pins 0..27
+  Gpio0To27,
+  /// GPIO pins 28..45
+  Gpio28To45,
+  /// GPIO pins 46..53
+  Gpio46To53,
+}
+
+impl PadGroup {
+  /// Get the pad group a specific GPIO pin belongs to. Returns ``None`` for pin numbers out of the range of the
+  /// available GPIO pins
+  pub fn of_pin(num: u32) -> Option<Self> {
+    match num {
+      0..=27 => Some(PadGroup::Gpio0To27),
+      28..=45 => Some(PadGroup::Gpio28To45),
+      46..=53 => Some(PadGroup::Gpio46To53),
+      _ => None,
+    }
+  }
+}
+
+/// The pad configuration of a [PadGroup]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PadConfig {
+  /// The drive strength of the pins in mA (2..16)
+  pub drive_strength_ma: u8,
+  /// Whether the slew rate of the pins is limited
+  pub slew_limited: bool,
+  /// Whether the input hysteresis of the pins is enabled
+  pub hysteresis: bool,
+}
+
+impl PadConfig {
+  /// Decode the pad configuration from the pad control register value
+  fn from_bits(bits: u32) -> Self {
+    Self {
+      // the drive strength is encoded in 2mA steps starting at 2mA
+      drive_strength_ma: ((bits & 0x7) as u8 + 1) * 2,
+      // a set bit means the slew rate is NOT limited
+      slew_limited: (bits & (1 << 4)) == 0,
+      hysteresis: (bits & (1 << 3)) != 0,
+    }
+  }
+}
+
+impl Gpio {
+  /// Read the current pad configuration of the given pin group
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let config = GPIO.with_mut(|gpio| gpio.pad_config(PadGroup::Gpio0To27));
+  /// println!("drive strength {}mA", config.drive_strength_ma);
+  /// # }
+  /// ```
+  pub fn pad_config(&self, group: PadGroup) -> PadConfig {
+    let bits = match group {
+      PadGroup::Gpio0To27 => PADS_GPIO_0_27::Register.get(),
+      PadGroup::Gpio28To45 => PADS_GPIO_28_45::Register.get(),
+      PadGroup::Gpio46To53 => PADS_GPIO_46_53::Register.get(),
+    };
+    PadConfig::from_bits(bits)
+  }
+}
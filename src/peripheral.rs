@@ -0,0 +1,330 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Peripheral pin mapping
+//!
+//! The canonical GPIO pins and the alternative function they need to take for the common peripherals of the Raspberry
+//! Pi, as given by the BCM2835 alternative function table. This saves cross-referencing the datasheet when setting up
+//! the pins of a peripheral.
+//!
+//! # Example
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! # fn doc() {
+//! let (num, alt) = peripheral::uart0_txd();
+//! assert_eq!((num, alt), (14, AltFunction::Alt0));
+//! let txd = GPIO.with_mut(|gpio| gpio.get_pin(num).unwrap().into_alt_f0());
+//! # }
+//! ```
+//!
+//! The ``Gpio::into_*`` functions switch a pin into a peripheral line and only accept the pin the line is mapped to.
+//! This prevents selecting the wrong alternative function for a pin. A wrong pin is handed back with the error.
+//! ```no_run
+//! # use ruspiro_gpio::*;
+//! # fn doc() {
+//! let pin = GPIO.with_mut(|gpio| gpio.get_pin(10)).unwrap();
+//! // GPIO 10 is SPI0 MOSI and not UART0 TXD, so this fails and returns the pin
+//! let mosi = match Gpio::into_uart0_txd(pin) {
+//!     Ok(_) => unreachable!(),
+//!     Err((_, pin)) => Gpio::into_spi0_mosi(pin),
+//! };
+//! assert!(mosi.is_ok());
+//! # }
+//! ```
+
+use crate::board::BoardRevision;
+use crate::interface::set_function;
+use crate::{function, AltFunction, Gpio, GpioError, Pin};
+
+/// UART0 transmit data line: GPIO 14, ALT0
+pub const fn uart0_txd() -> (u32, AltFunction) {
+  (14, AltFunction::Alt0)
+}
+
+/// UART0 receive data line: GPIO 15, ALT0
+pub const fn uart0_rxd() -> (u32, AltFunction) {
+  (15, AltFunction::Alt0)
+}
+
+/// UART0 clear to send line: GPIO 16, ALT3
+pub const fn uart0_cts() -> (u32, AltFunction) {
+  (16, AltFunction::Alt3)
+}
+
+/// UART0 request to send line: GPIO 17, ALT3
+pub const fn uart0_rts() -> (u32, AltFunction) {
+  (17, AltFunction::Alt3)
+}
+
+/// SPI0 chip enable 1 line: GPIO 7, ALT0
+pub const fn spi0_ce1() -> (u32, AltFunction) {
+  (7, AltFunction::Alt0)
+}
+
+/// SPI0 chip enable 0 line: GPIO 8, ALT0
+pub const fn spi0_ce0() -> (u32, AltFunction) {
+  (8, AltFunction::Alt0)
+}
+
+/// SPI0 master in slave out line: GPIO 9, ALT0
+pub const fn spi0_miso() -> (u32, AltFunction) {
+  (9, AltFunction::Alt0)
+}
+
+/// SPI0 master out slave in line: GPIO 10, ALT0
+pub const fn spi0_mosi() -> (u32, AltFunction) {
+  (10, AltFunction::Alt0)
+}
+
+/// SPI0 clock line: GPIO 11, ALT0
+pub const fn spi0_sclk() -> (u32, AltFunction) {
+  (11, AltFunction::Alt0)
+}
+
+/// I2C1 data line: GPIO 2, ALT0
+pub const fn i2c1_sda() -> (u32, AltFunction) {
+  (2, AltFunction::Alt0)
+}
+
+/// I2C1 clock line: GPIO 3, ALT0
+pub const fn i2c1_scl() -> (u32, AltFunction) {
+  (3, AltFunction::Alt0)
+}
+
+/// PWM channel 0 output: GPIO 18, ALT5. Alternatively available at GPIO 12, ALT0
+pub const fn pwm0() -> (u32, AltFunction) {
+  (18, AltFunction::Alt5)
+}
+
+/// PWM channel 1 output: GPIO 19, ALT5. Alternatively available at GPIO 13, ALT0
+pub const fn pwm1() -> (u32, AltFunction) {
+  (19, AltFunction::Alt5)
+}
+
+/// I2C0 data line: GPIO 0, ALT0
+pub const fn i2c0_sda() -> (u32, AltFunction) {
+  (0, AltFunction::Alt0)
+}
+
+/// I2C0 clock line: GPIO 1, ALT0
+pub const fn i2c0_scl() -> (u32, AltFunction) {
+  (1, AltFunction::Alt0)
+}
+
+/// The data and clock line of the I2C bus available at the GPIO header (pin 3 and 5) of the given
+/// board revision. This is I2C0 on the first revision of the Raspberry Pi 1 Model B and I2C1 on all
+/// later boards.
+pub const fn header_i2c(revision: BoardRevision) -> ((u32, AltFunction), (u32, AltFunction)) {
+  if revision.is_pi1_rev1() {
+    (i2c0_sda(), i2c0_scl())
+  } else {
+    (i2c1_sda(), i2c1_scl())
+  }
+}
+
+// Verify some of the mapped pins at compile time against the BCM2835 alternative function table. A
+// wrong pin will fail the build with a mismatched array length
+const _: [(); 14] = [(); uart0_txd().0 as usize];
+const _: [(); 10] = [(); spi0_mosi().0 as usize];
+const _: [(); 2] = [(); i2c1_sda().0 as usize];
+const _: [(); 18] = [(); pwm0().0 as usize];
+
+/// The peripherals with a canonical pin mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Peripheral {
+  /// UART0 data lines, TXD and RXD
+  Uart0,
+  /// UART0 data and flow control lines, TXD, RXD, CTS and RTS
+  Uart0FlowControl,
+  /// SPI0 lines, CE1, CE0, MISO, MOSI and SCLK
+  Spi0,
+  /// I2C1 lines, SDA and SCL
+  I2c1,
+  /// PWM channel 0 and 1 outputs
+  Pwm,
+}
+
+const UART0: [(u32, AltFunction); 2] = [uart0_txd(), uart0_rxd()];
+const UART0_FLOW_CONTROL: [(u32, AltFunction); 4] =
+  [uart0_txd(), uart0_rxd(), uart0_cts(), uart0_rts()];
+const SPI0: [(u32, AltFunction); 5] = [
+  spi0_ce1(),
+  spi0_ce0(),
+  spi0_miso(),
+  spi0_mosi(),
+  spi0_sclk(),
+];
+const I2C1: [(u32, AltFunction); 2] = [i2c1_sda(), i2c1_scl()];
+const PWM: [(u32, AltFunction); 2] = [pwm0(), pwm1()];
+
+impl Peripheral {
+  /// The GPIO pins and their alternative function used by the peripheral
+  pub fn pins(self) -> &'static [(u32, AltFunction)] {
+    match self {
+      Peripheral::Uart0 => &UART0,
+      Peripheral::Uart0FlowControl => &UART0_FLOW_CONTROL,
+      Peripheral::Spi0 => &SPI0,
+      Peripheral::I2c1 => &I2C1,
+      Peripheral::Pwm => &PWM,
+    }
+  }
+}
+
+/// The pins acquired for a peripheral with [Gpio::configure_peripheral]. Like a [Pin] they are
+/// released once dropped.
+pub struct PeripheralPins {
+  peripheral: Peripheral,
+}
+
+impl PeripheralPins {
+  /// The peripheral the pins are configured for
+  pub fn peripheral(&self) -> Peripheral {
+    self.peripheral
+  }
+
+  /// The GPIO pins and their alternative function
+  pub fn pins(&self) -> &'static [(u32, AltFunction)] {
+    self.peripheral.pins()
+  }
+}
+
+impl Gpio {
+  /// Acquire the canonical pins of the peripheral and switch them to the alternative function the
+  /// peripheral requires.
+  /// Returns an Err if any of the pins is already in use or reserved, in this case no pin is
+  /// acquired or configured.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # use ruspiro_gpio::peripheral::*;
+  /// # fn doc() {
+  /// let uart = GPIO.with_mut(|gpio| gpio.configure_peripheral(Peripheral::Uart0)).unwrap();
+  /// # }
+  /// ```
+  pub fn configure_peripheral(
+    &mut self,
+    peripheral: Peripheral,
+  ) -> Result<PeripheralPins, GpioError> {
+    let mut masks = [0u32; 2];
+    for &(num, _) in peripheral.pins() {
+      masks[(num / 32) as usize] |= 1 << (num & 31);
+    }
+    self.acquire_all(masks)?;
+    for &(num, alt) in peripheral.pins() {
+      set_function(num, alt.into());
+      crate::claim_pin(num);
+    }
+
+    Ok(PeripheralPins { peripheral })
+  }
+
+  /// Release all pins of the peripheral to allow re-usage. The function of the pins is not changed.
+  pub fn free_peripheral(&mut self, pins: PeripheralPins) {
+    for &(num, _) in pins.pins() {
+      crate::unclaim_pin(num);
+      let _ = self.free_pin_keep_state(num);
+    }
+    // the pins are released already
+    core::mem::forget(pins);
+  }
+}
+
+impl Drop for PeripheralPins {
+  fn drop(&mut self) {
+    for &(num, _) in self.pins() {
+      crate::release_dropped_pin(num);
+    }
+  }
+}
+
+/// Verify the pin is the one the peripheral line is mapped to
+fn verify_pin(num: u32, (line_num, _): (u32, AltFunction)) -> Result<(), GpioError> {
+  if num == line_num {
+    Ok(())
+  } else {
+    Err(GpioError::WrongPeripheralPin(num))
+  }
+}
+
+macro_rules! peripheral_pins {
+  ($($name:ident => $mapping:ident, $into:ident, $func:ident, $line:literal;)*) => {
+    impl Gpio {
+      $(
+        #[doc = concat!("Switch the pin into the ", $line, " line, see [", stringify!($mapping), "].")]
+        #[doc = "Returns an Err with the unchanged pin if it is not the one this line is mapped to."]
+        #[allow(clippy::type_complexity)]
+        pub fn $name<FUNC, PUD>(
+          pin: Pin<FUNC, PUD>,
+        ) -> Result<Pin<function::$func, PUD>, (GpioError, Pin<FUNC, PUD>)> {
+          match verify_pin(pin.num, $mapping()) {
+            Ok(()) => Ok(pin.$into()),
+            Err(err) => Err((err, pin)),
+          }
+        }
+      )*
+    }
+  };
+}
+
+peripheral_pins! {
+  into_uart0_txd => uart0_txd, into_alt_f0, AltFunc0, "UART0 transmit data";
+  into_uart0_rxd => uart0_rxd, into_alt_f0, AltFunc0, "UART0 receive data";
+  into_uart0_cts => uart0_cts, into_alt_f3, AltFunc3, "UART0 clear to send";
+  into_uart0_rts => uart0_rts, into_alt_f3, AltFunc3, "UART0 request to send";
+  into_spi0_ce1 => spi0_ce1, into_alt_f0, AltFunc0, "SPI0 chip enable 1";
+  into_spi0_ce0 => spi0_ce0, into_alt_f0, AltFunc0, "SPI0 chip enable 0";
+  into_spi0_miso => spi0_miso, into_alt_f0, AltFunc0, "SPI0 master in slave out";
+  into_spi0_mosi => spi0_mosi, into_alt_f0, AltFunc0, "SPI0 master out slave in";
+  into_spi0_sclk => spi0_sclk, into_alt_f0, AltFunc0, "SPI0 clock";
+  into_i2c1_sda => i2c1_sda, into_alt_f0, AltFunc0, "I2C1 data";
+  into_i2c1_scl => i2c1_scl, into_alt_f0, AltFunc0, "I2C1 clock";
+  into_pwm0 => pwm0, into_alt_f5, AltFunc5, "PWM channel 0 output";
+  into_pwm1 => pwm1, into_alt_f5, AltFunc5, "PWM channel 1 output";
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lines_match_bcm2835_alt_function_table() {
+    assert_eq!(uart0_txd(), (14, AltFunction::Alt0));
+    assert_eq!(uart0_rxd(), (15, AltFunction::Alt0));
+    assert_eq!(uart0_cts(), (16, AltFunction::Alt3));
+    assert_eq!(uart0_rts(), (17, AltFunction::Alt3));
+    assert_eq!(spi0_ce1(), (7, AltFunction::Alt0));
+    assert_eq!(spi0_ce0(), (8, AltFunction::Alt0));
+    assert_eq!(spi0_miso(), (9, AltFunction::Alt0));
+    assert_eq!(spi0_mosi(), (10, AltFunction::Alt0));
+    assert_eq!(spi0_sclk(), (11, AltFunction::Alt0));
+    assert_eq!(i2c1_sda(), (2, AltFunction::Alt0));
+    assert_eq!(i2c1_scl(), (3, AltFunction::Alt0));
+    assert_eq!(pwm0(), (18, AltFunction::Alt5));
+    assert_eq!(pwm1(), (19, AltFunction::Alt5));
+  }
+
+  #[test]
+  fn peripheral_pins_use_the_line_mapping() {
+    assert_eq!(Peripheral::Uart0.pins(), &[uart0_txd(), uart0_rxd()]);
+    assert_eq!(Peripheral::I2c1.pins(), &[i2c1_sda(), i2c1_scl()]);
+    assert_eq!(Peripheral::Pwm.pins(), &[pwm0(), pwm1()]);
+    assert_eq!(Peripheral::Spi0.pins().len(), 5);
+  }
+
+  #[test]
+  fn verify_pin_accepts_only_the_mapped_pin() {
+    assert_eq!(verify_pin(14, uart0_txd()), Ok(()));
+    assert_eq!(verify_pin(10, spi0_mosi()), Ok(()));
+    assert_eq!(
+      verify_pin(10, uart0_txd()),
+      Err(GpioError::WrongPeripheralPin(10))
+    );
+    assert_eq!(
+      verify_pin(12, pwm0()),
+      Err(GpioError::WrongPeripheralPin(12))
+    );
+  }
+}
@@ -10,10 +10,19 @@
 //! zero-sizes-type generics argument to ensure compile time safety when using a pin that has specific requirements
 //!
 use crate::interface::*;
+#[cfg(feature = "mock-mmio")]
+use crate::mock::registers::{ReadOnly, ReadWrite, RegisterField, WriteOnly};
+use crate::time::TimeSource;
+use crate::{GpioError, GpioEvent};
+use core::cell::Cell;
+#[cfg(not(feature = "mock-mmio"))]
 use ruspiro_mmio_register::{ReadOnly, ReadWrite, RegisterField, WriteOnly};
 
 /// Representation of a GPIO pin that can have specific features. Those features are described with generic arguments to
 /// define the pin e.g. as an output pin with disabled PullUp/Down.
+/// A pin is released once it is dropped, so it can be acquired again with [Gpio::get_pin](crate::Gpio::get_pin). As the
+/// pin could be dropped while the [GPIO](crate::GPIO) is locked, the release is only recorded and applied by the
+/// ``Gpio`` the next time a pin is acquired.
 #[allow(dead_code)]
 pub struct Pin<FUNCTION, PUD> {
   pub(crate) num: u32,
@@ -26,17 +35,41 @@ pub struct Pin<FUNCTION, PUD> {
 
 /// Type states for the FUNCTION generic argument of the pin.
 pub(crate) mod function {
+  use crate::AltFunction;
+  use core::cell::Cell;
+
   pub struct Input;
-  pub struct Output;
+  pub struct Output {
+    /// the level last written to the output pin
+    pub(crate) driven: Cell<bool>,
+  }
   pub struct AltFunc0;
   pub struct AltFunc1;
   pub struct AltFunc3;
   pub struct AltFunc2;
   pub struct AltFunc4;
   pub struct AltFunc5;
+  /// alternative function only known at runtime
+  pub struct AltUnknown {
+    pub(crate) alt: AltFunction,
+  }
   pub struct Unknown;
 }
 
+/// Marker for the pin functions that allow edge and level event detection. The detection works on
+/// the physical level of the pin, so this is the case for input pins and pins with an alternative
+/// function (e.g. a UART RX line that should wake up the system), but not for output pins.
+pub trait EventCapable {}
+
+impl EventCapable for function::Input {}
+impl EventCapable for function::AltFunc0 {}
+impl EventCapable for function::AltFunc1 {}
+impl EventCapable for function::AltFunc2 {}
+impl EventCapable for function::AltFunc3 {}
+impl EventCapable for function::AltFunc4 {}
+impl EventCapable for function::AltFunc5 {}
+impl EventCapable for function::AltUnknown {}
+
 /// Type states for the PUD template argument of the pin
 pub(crate) mod pud {
   pub struct PullDown;
@@ -48,10 +81,11 @@ pub(crate) mod pud {
 /// Functions available for any kind of pin
 impl<FUNC, PUD> Pin<FUNC, PUD> {
   /// Create a new ``Pin`` with an unknown function and PUD settings.
+  /// **HINT*: There must only be one ``Pin`` for a specific pin number at any time. Pins are only
+  /// handed out by the [Gpio](crate::Gpio) which keeps track of the pins in use.
   #[allow(clippy::new_ret_no_self)]
-  pub fn new(num: u32) -> Pin<function::Unknown, pud::Unknown> {
-    let fsel_num = num / 10;
-    let fsel_shift = (num % 10) * 3;
+  pub(crate) fn new(num: u32) -> Pin<function::Unknown, pud::Unknown> {
+    let (fsel_num, fsel_shift) = gpfsel_for(num);
     Pin {
       num,
       config: PinConfig {
@@ -81,12 +115,8 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
           GPLEV1::Register
         },
         setclr_val: 1 << (num % 32),
-        pudclk: if num < 32 {
-          GPPUDCLK0::Register
-        } else {
-          GPPUDCLK1::Register
-        },
         pud_val: 1 << (num % 32),
+        claim: PinClaim::new(num),
       },
       function: function::Unknown,
       pud: pud::Unknown,
@@ -95,10 +125,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into an input pin
   pub fn into_input(self) -> Pin<function::Input, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Input as u32);
+    self.select_function(Function::Input);
     Pin {
       num: self.num,
       config: self.config,
@@ -109,24 +136,21 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into an output pin
   pub fn into_output(self) -> Pin<function::Output, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Output as u32);
+    self.select_function(Function::Output);
+    // the output level is initially tracked as the current level of the pin
+    let driven = Cell::new(self.read_level());
+    track_levels(pin_bank(self.num), self.config.setclr_val, driven.get());
     Pin {
       num: self.num,
       config: self.config,
-      function: function::Output,
+      function: function::Output { driven },
       pud: self.pud,
     }
   }
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f0(self) -> Pin<function::AltFunc0, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt0 as u32);
+    self.select_function(Function::Alt0);
     Pin {
       num: self.num,
       config: self.config,
@@ -137,10 +161,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f1(self) -> Pin<function::AltFunc1, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt1 as u32);
+    self.select_function(Function::Alt1);
     Pin {
       num: self.num,
       config: self.config,
@@ -151,10 +172,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f2(self) -> Pin<function::AltFunc2, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt2 as u32);
+    self.select_function(Function::Alt2);
     Pin {
       num: self.num,
       config: self.config,
@@ -165,10 +183,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f3(self) -> Pin<function::AltFunc3, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt3 as u32);
+    self.select_function(Function::Alt3);
     Pin {
       num: self.num,
       config: self.config,
@@ -179,10 +194,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f4(self) -> Pin<function::AltFunc4, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt4 as u32);
+    self.select_function(Function::Alt4);
     Pin {
       num: self.num,
       config: self.config,
@@ -193,10 +205,7 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
 
   /// switch any pin into a pin with active alt function 0
   pub fn into_alt_f5(self) -> Pin<function::AltFunc5, PUD> {
-    self
-      .config
-      .fsel
-      .modify(self.config.fsel_field, Function::Alt5 as u32);
+    self.select_function(Function::Alt5);
     Pin {
       num: self.num,
       config: self.config,
@@ -205,6 +214,82 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
     }
   }
 
+  /// Switch the pin into the given alternative function. Other than the ``into_alt_fN`` functions
+  /// the alternative function is only known at runtime, e.g. when taken from a pin-mux table, so
+  /// all alternative functions result in the same ``Pin`` type.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let pin_mux = [(14, AltFunction::Alt0), (15, AltFunction::Alt0)];
+  /// for &(num, alt) in pin_mux.iter() {
+  ///     let pin = GPIO.with_mut(|gpio| gpio.get_pin(num)).unwrap().into_alt(alt);
+  ///     assert_eq!(pin.alt_function(), alt);
+  /// }
+  /// # }
+  /// ```
+  pub fn into_alt(self, alt: AltFunction) -> Pin<function::AltUnknown, PUD> {
+    self.select_function(alt.into());
+    Pin {
+      num: self.num,
+      config: self.config,
+      function: function::AltUnknown { alt },
+      pud: self.pud,
+    }
+  }
+
+  /// Switch the pin into the given alternative function with PullUp enabled. The PullUp is applied
+  /// before the function is switched, so a bus line like the I2C SDA is already pulled to its idle
+  /// level once the peripheral takes over the pin and never floats.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let sda = GPIO
+  ///     .with_mut(|gpio| gpio.get_pin(2))
+  ///     .unwrap()
+  ///     .into_alt_with_pud_up(AltFunction::Alt0);
+  /// # }
+  /// ```
+  pub fn into_alt_with_pud_up(self, alt: AltFunction) -> Pin<function::AltUnknown, pud::PullUp> {
+    self.into_alt_with_pud(alt, Pud::PullUp, pud::PullUp)
+  }
+
+  /// Switch the pin into the given alternative function with PullDown enabled, see
+  /// [Pin::into_alt_with_pud_up]
+  pub fn into_alt_with_pud_down(
+    self,
+    alt: AltFunction,
+  ) -> Pin<function::AltUnknown, pud::PullDown> {
+    self.into_alt_with_pud(alt, Pud::PullDown, pud::PullDown)
+  }
+
+  /// Switch the pin into the given alternative function with PullUp/Down disabled, see
+  /// [Pin::into_alt_with_pud_up]
+  pub fn into_alt_with_pud_disabled(
+    self,
+    alt: AltFunction,
+  ) -> Pin<function::AltUnknown, pud::Disabled> {
+    self.into_alt_with_pud(alt, Pud::Disabled, pud::Disabled)
+  }
+
+  /// Apply the PullUp/Down setting first and switch the pin into the alternative function afterwards
+  fn into_alt_with_pud<P>(
+    self,
+    alt: AltFunction,
+    pud: Pud,
+    state: P,
+  ) -> Pin<function::AltUnknown, P> {
+    self.set_pud(pud);
+    self.select_function(alt.into());
+    Pin {
+      num: self.num,
+      config: self.config,
+      function: function::AltUnknown { alt },
+      pud: state,
+    }
+  }
+
   /// Disable PullUp/Down for the pin
   pub fn into_pud_disabled(self) -> Pin<FUNC, pud::Disabled> {
     self.set_pud(Pud::Disabled);
@@ -241,50 +326,498 @@ impl<FUNC, PUD> Pin<FUNC, PUD> {
     }
   }
 
+  /// The number of the GPIO pin
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let pin = GPIO.with_mut(|gpio| gpio.get_pin(17)).unwrap();
+  /// assert_eq!(pin.num(), 17);
+  /// # }
+  /// ```
+  pub fn num(&self) -> u32 {
+    self.num
+  }
+
+  /// Consume the pin and return its number. Other than dropping the pin it stays in use, so the
+  /// number could be handed to [Gpio::free_pin](crate::Gpio::free_pin) to release it.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(17).unwrap().into_output();
+  ///     let num = pin.into_num();
+  ///     gpio.free_pin(num).unwrap();
+  /// });
+  /// # }
+  /// ```
+  pub fn into_num(self) -> u32 {
+    let num = self.num;
+    // skip the release of the pin on drop
+    core::mem::forget(self);
+    crate::unclaim_pin(num);
+    num
+  }
+
+  /// Read the function the pin is currently configured for from the function select register
+  pub fn current_function(&self) -> Function {
+    get_function(self.num)
+  }
+
+  /// Returns whether the pin is currently configured as input
+  pub fn is_input(&self) -> bool {
+    self.current_function() == Function::Input
+  }
+
+  /// Returns whether the pin is currently configured as output
+  pub fn is_output(&self) -> bool {
+    self.current_function() == Function::Output
+  }
+
+  /// Write the pin bit to the set register regardless of the function the pin is configured for.
+  /// This is intended for bring-up and testing only.
+  /// # Safety
+  /// This bypasses the function state guarantees of the [Pin]. The output level register is
+  /// written even if the pin is not an output and the level last driven is not tracked for output
+  /// pins. Any peripheral using the pin with an alternative function might be affected once the pin
+  /// is switched to an output.
+  pub unsafe fn force_high(&self) {
+    self.config.set.set(self.config.setclr_val);
+  }
+
+  /// Write the pin bit to the clear register regardless of the function the pin is configured for.
+  /// This is intended for bring-up and testing only.
+  /// # Safety
+  /// This bypasses the function state guarantees of the [Pin]. The output level register is
+  /// written even if the pin is not an output and the level last driven is not tracked for output
+  /// pins. Any peripheral using the pin with an alternative function might be affected once the pin
+  /// is switched to an output.
+  pub unsafe fn force_low(&self) {
+    self.config.clear.set(self.config.setclr_val);
+  }
+
+  /// Read the current level of the pin, ``true`` for high and ``false`` for low
+  pub(crate) fn read_level(&self) -> bool {
+    (self.config.level.get() & self.config.setclr_val) != 0
+  }
+
+  fn select_function(&self, function: Function) {
+    self
+      .config
+      .fsel
+      .modify(self.config.fsel_field, function as u32);
+    // ensure the function select is written before any further access to the GPIO
+    memory_barrier();
+  }
+
   fn set_pud(&self, pud: Pud) {
-    // do a pud change cycle:
-    // 1. write the desired pud control value to the PUD control register
-    GPPUD::Register.modify(GPPUD::PUD, pud as u32);
-    // 2. wait 150 cycles
-    for _ in 0..150 {
-      unsafe { asm!("NOP") }
-    }
-    // 3. write the pin to upate into the PUDCLCK register
-    self.config.pudclk.set(self.config.pud_val);
-    // 4. wait 150 cycles to settle the new settings
-    for _ in 0..150 {
-      unsafe { asm!("NOP") }
-    }
-    // 5. clear the pud control value in the PUD control register
-    GPPUD::Register.set(0x0);
-    // 6. write the pin to the PUDCLCK register again to finish the update cycle
-    self.config.pudclk.set(self.config.pud_val);
+    match pin_bank(self.num) {
+      GpioBank::Bank0 => pud_sequence(pud, self.config.pud_val, 0),
+      GpioBank::Bank1 => pud_sequence(pud, 0, self.config.pud_val),
+    }
+  }
+}
+
+impl<FUNC, PUD> From<Pin<FUNC, PUD>> for u32 {
+  fn from(pin: Pin<FUNC, PUD>) -> Self {
+    pin.into_num()
+  }
+}
+
+/// Functions available only for a pin with unknown function and PUD settings
+impl Pin<function::Unknown, pud::Unknown> {
+  /// switch the pin into a floating input pin with disabled PullUp/Down. This is the same as
+  /// ``into_input().into_pud_disabled()``
+  pub fn into_floating_input(self) -> Pin<function::Input, pud::Disabled> {
+    self.into_input().into_pud_disabled()
+  }
+}
+
+/// Functions available only for a pin with an alternative function known at runtime
+impl<PUD> Pin<function::AltUnknown, PUD> {
+  /// The alternative function the pin has been switched to
+  pub fn alt_function(&self) -> AltFunction {
+    self.function.alt
+  }
+}
+
+/// The logic level of a pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+  High,
+  Low,
+}
+
+/// Functions available only for an Input pin with any PUD setting
+impl<PUD> Pin<function::Input, PUD> {
+  /// Read the current logic level of the pin
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let button = GPIO.with_mut(|gpio| gpio.get_pin(17)).unwrap().into_input().into_pud_up();
+  /// if button.level() == Level::Low {
+  ///     println!("button pressed");
+  /// }
+  /// # }
+  /// ```
+  pub fn level(&self) -> Level {
+    if self.read_level() {
+      Level::High
+    } else {
+      Level::Low
+    }
+  }
+
+  /// Returns whether the pin currently reads high
+  pub fn is_high(&self) -> bool {
+    self.read_level()
+  }
+
+  /// Returns whether the pin currently reads low
+  pub fn is_low(&self) -> bool {
+    !self.is_high()
+  }
+
+  /// Check whether the condition of the event is present. For the level events ([GpioEvent::High]
+  /// and [GpioEvent::Low]) this reads the current level of the pin. For the edge events this
+  /// checks whether an event has been detected for the pin and acknowledges it. As the event
+  /// detect status does not tell which edge has been detected, the corresponding edge detection
+  /// needs to be armed for the pin.
+  /// **HINT*: Once an event handler is registered for this pin the interrupt handler acknowledges
+  /// the detected edges before they could be seen here.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let pin = GPIO.with_mut(|gpio| gpio.get_pin(17)).unwrap().into_input();
+  /// if pin.event_pending(GpioEvent::High) {
+  ///     println!("pin is high");
+  /// }
+  /// # }
+  /// ```
+  pub fn event_pending(&self, event: GpioEvent) -> bool {
+    match event {
+      GpioEvent::High => self.read_level(),
+      GpioEvent::Low => !self.read_level(),
+      _ => {
+        let bank = pin_bank(self.num);
+        let pending = get_detected_events(bank) & self.config.setclr_val;
+        if pending != 0 {
+          acknowledge_detected_events(pending, bank);
+        }
+        pending != 0
+      }
+    }
+  }
+
+  /// Block until the event occurs on the pin. The detection of the event is armed for the pin,
+  /// replacing any other event detection of this pin, and the event detect status is polled until
+  /// the event has been detected. Afterwards the status is acknowledged and the event detection of
+  /// the pin is disabled. As this polls the status, the interrupts do not need to be enabled.
+  /// **HINT*: This never returns if the event does not occur, see [Pin::wait_for_edge_timeout].
+  /// Once an event handler is registered for this pin the interrupt handler acknowledges the
+  /// detected events before they could be seen here.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let button = GPIO.with_mut(|gpio| gpio.get_pin(26)).unwrap().into_input().into_pud_up();
+  /// button.wait_for_edge(GpioEvent::FallingEdge);
+  /// println!("button pressed");
+  /// # }
+  /// ```
+  pub fn wait_for_edge(&self, event: GpioEvent) {
+    self.arm_event(event);
+    while !self.take_detected_event() {}
+    deactivate_all_detect_events(self.num);
+  }
+
+  /// Block until the event occurs on the pin like [Pin::wait_for_edge], but give up after
+  /// ``timeout`` micro seconds from calling this function.
+  /// Returns an Err if the event did not occur in time.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl TimeSource for Timer { fn now_us(&self) -> u64 { 0 } }
+  /// # fn doc() {
+  /// let ready = GPIO.with_mut(|gpio| gpio.get_pin(25)).unwrap().into_input();
+  /// if ready.wait_for_edge_timeout(GpioEvent::RisingEdge, &Timer, 10_000).is_err() {
+  ///     println!("device not ready");
+  /// }
+  /// # }
+  /// ```
+  pub fn wait_for_edge_timeout(
+    &self,
+    event: GpioEvent,
+    timer: &impl TimeSource,
+    timeout: u64,
+  ) -> Result<(), GpioError> {
+    let start = timer.now_us();
+    self.arm_event(event);
+    let mut result = Ok(());
+    while !self.take_detected_event() {
+      if timer.elapsed_us(start, timer.now_us()) > timeout {
+        result = Err(GpioError::Timeout(self.num));
+        break;
+      }
+    }
+    deactivate_all_detect_events(self.num);
+    result
+  }
+
+  /// Arm the detection of the event for the pin, discarding any event detected before
+  fn arm_event(&self, event: GpioEvent) {
+    set_detect_events(self.num, event.into());
+    acknowledge_detected_events(self.config.setclr_val, pin_bank(self.num));
+  }
+
+  /// Check whether an event has been detected for the pin and acknowledge it
+  fn take_detected_event(&self) -> bool {
+    let bank = pin_bank(self.num);
+    let detected = get_detected_events(bank) & self.config.setclr_val != 0;
+    if detected {
+      acknowledge_detected_events(self.config.setclr_val, bank);
+    }
+    detected
+  }
+
+  /// Measure how long the pin stays at the given level. This waits for the pin to reach the level
+  /// and returns the time in micro seconds until it leaves the level again. Returns ``None`` if
+  /// this does not happen within ``timeout`` micro seconds from calling this function.
+  /// **HINT*: This busy polls the level of the pin, so the resolution is limited by the time
+  /// source and the time it takes to read the level register.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl TimeSource for Timer { fn now_us(&self) -> u64 { 0 } }
+  /// # fn doc() {
+  /// let echo = GPIO.with_mut(|gpio| gpio.get_pin(24)).unwrap().into_input();
+  /// if let Some(width) = echo.measure_pulse_width(true, &Timer, 30_000) {
+  ///     println!("echo pulse took {}µs", width);
+  /// }
+  /// # }
+  /// ```
+  pub fn measure_pulse_width(
+    &self,
+    level: bool,
+    timer: &impl TimeSource,
+    timeout: u64,
+  ) -> Option<u64> {
+    let start = timer.now_us();
+    let timed_out = |now: u64| timer.elapsed_us(start, now) > timeout;
+    // wait for the pin to reach the level
+    while self.read_level() != level {
+      if timed_out(timer.now_us()) {
+        return None;
+      }
+    }
+    let pulse_start = timer.now_us();
+    // wait for the pin to leave the level
+    loop {
+      let now = timer.now_us();
+      if self.read_level() != level {
+        return Some(timer.elapsed_us(pulse_start, now));
+      }
+      if timed_out(now) {
+        return None;
+      }
+    }
   }
 }
 
 /// Functions available only for an Output pin with any PUD setting
 impl<PUD> Pin<function::Output, PUD> {
+  /// Drive the pin high. With the ``paranoid`` feature active the pin is not driven if its function
+  /// has been changed behind this handle, use [Pin::try_high] to get notified about it.
   pub fn high(&self) {
-    // write the pin bit to the set register to set the pin to high
-    self.config.set.set(self.config.setclr_val);
+    if self.verify_output().is_ok() {
+      self.drive(true);
+    }
   }
 
+  /// Drive the pin low. With the ``paranoid`` feature active the pin is not driven if its function
+  /// has been changed behind this handle, use [Pin::try_low] to get notified about it.
   pub fn low(&self) {
-    // write the pin bit to the clear register to set the pin to low
-    self.config.clear.set(self.config.setclr_val);
+    if self.verify_output().is_ok() {
+      self.drive(false);
+    }
   }
 
+  /// Drive the pin high like [Pin::high], but verify the pin is still configured as output first.
+  /// Returns an Err if the function of the pin has been changed behind this handle, e.g. by another
+  /// driver, in this case the pin is not driven.
+  pub fn try_high(&self) -> Result<(), GpioError> {
+    self.check_output()?;
+    self.drive(true);
+    Ok(())
+  }
+
+  /// Drive the pin low like [Pin::low], but verify the pin is still configured as output first.
+  /// Returns an Err if the function of the pin has been changed behind this handle, e.g. by another
+  /// driver, in this case the pin is not driven.
+  pub fn try_low(&self) -> Result<(), GpioError> {
+    self.check_output()?;
+    self.drive(false);
+    Ok(())
+  }
+
+  /// Toggle the pin like [Pin::toggle], but verify the pin is still configured as output first.
+  /// Returns an Err if the function of the pin has been changed behind this handle, e.g. by another
+  /// driver, in this case the pin is not driven.
+  pub fn try_toggle(&self) -> Result<(), GpioError> {
+    self.check_output()?;
+    self.drive(!self.function.driven.get());
+    Ok(())
+  }
+
+  /// Write the pin bit to the set or clear register and record the level driven
+  fn drive(&self, level: bool) {
+    if level {
+      // write the pin bit to the set register to set the pin to high
+      self.config.set.set(self.config.setclr_val);
+    } else {
+      // write the pin bit to the clear register to set the pin to low
+      self.config.clear.set(self.config.setclr_val);
+    }
+    self.function.driven.set(level);
+    track_levels(pin_bank(self.num), self.config.setclr_val, level);
+  }
+
+  /// Verify the pin is still configured as output. This detects a pin that has been switched to a
+  /// different function behind this handle.
+  fn check_output(&self) -> Result<(), GpioError> {
+    if get_function(self.num) == Function::Output {
+      Ok(())
+    } else {
+      Err(GpioError::InvalidFunction(self.num))
+    }
+  }
+
+  /// With the ``paranoid`` feature active verify the pin is still configured as output before it
+  /// is driven. Without the feature this compiles to nothing.
+  #[cfg(feature = "paranoid")]
+  #[inline(always)]
+  fn verify_output(&self) -> Result<(), GpioError> {
+    self.check_output()
+  }
+
+  #[cfg(not(feature = "paranoid"))]
+  #[inline(always)]
+  fn verify_output(&self) -> Result<(), GpioError> {
+    Ok(())
+  }
+
+  /// Toggle the pin between high and low based on the level last written to it. This does not
+  /// read the actual level of the pin, so it is also correct for heavily loaded pins where the
+  /// level does not reflect the driven state.
+  /// **HINT*: Only the writes through this ``Pin`` are taken into account. If the pin is also
+  /// driven from elsewhere, e.g. an interrupt handler using [Gpio::set_pins](crate::Gpio::set_pins),
+  /// the toggle might write the level the pin already has. Use [Pin::toggle_atomic] in this case.
   pub fn toggle(&self) {
+    if self.function.driven.get() {
+      self.low();
+    } else {
+      self.high();
+    }
+  }
+
+  /// Toggle the pin between high and low based on the level last written to it through any path
+  /// of this crate, e.g. also by a [Port](crate::Port) or [Gpio::set_pins](crate::Gpio::set_pins).
+  /// The IRQ's are masked on the current core while the level is determined and written, so an
+  /// interrupt handler of this core driving the same pin can't interleave and the toggle is never
+  /// lost.
+  /// **HINT*: This is only atomic with respect to the current core. Code running on another core,
+  /// including interrupt handlers routed to it, could still drive the pin in between.
+  pub fn toggle_atomic(&self) {
+    without_interrupts(|| {
+      if tracked_level(self.num) {
+        self.low();
+      } else {
+        self.high();
+      }
+    });
+  }
+
+  /// Toggle the pin between high and low based on the actual level read from the pin.
+  /// **HINT*: The level is read and written in separate steps. If the pin is driven from elsewhere
+  /// in between, e.g. by an interrupt handler, the toggle is lost. Use [Pin::toggle_atomic] in this
+  /// case.
+  pub fn toggle_from_level(&self) {
     // get the current level of the pin and toggle it's state
-    if (self.config.level.get() & self.config.setclr_val) == 0 {
+    if self.read_level() {
+      self.low();
+    } else {
+      self.high();
+    }
+  }
+
+  /// Record the level written to the pin by means other than [Pin::high] or [Pin::low], e.g. a
+  /// write to the whole bank
+  pub(crate) fn track_driven(&self, level: bool) {
+    self.function.driven.set(level);
+  }
+
+  /// Returns whether the pin has last been driven high
+  pub fn is_set_high(&self) -> bool {
+    self.function.driven.get()
+  }
+
+  /// Returns whether the pin has last been driven low
+  pub fn is_set_low(&self) -> bool {
+    !self.function.driven.get()
+  }
+
+  /// Drive the pin to the given level while the closure runs. Afterwards the pin is restored to the level last
+  /// written to it before this call. The level is restored as well if the closure panics.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// # let cs = GPIO.with_mut(|gpio| gpio.get_pin(8)).unwrap().into_output();
+  /// cs.high();
+  /// // assert the active low chip-select while the transaction runs
+  /// cs.with_driven(false, || {
+  ///   // transfer data
+  /// });
+  /// # }
+  /// ```
+  pub fn with_driven<R, F: FnOnce() -> R>(&self, level: bool, f: F) -> R {
+    let _guard = DrivenGuard {
+      pin: self,
+      level: self.function.driven.get(),
+    };
+    if level {
       self.high();
     } else {
       self.low();
     }
+    f()
   }
 }
 
-#[derive(Clone)]
+/// Restores the level of an output pin when dropped
+struct DrivenGuard<'a, PUD> {
+  pin: &'a Pin<function::Output, PUD>,
+  level: bool,
+}
+
+impl<PUD> Drop for DrivenGuard<'_, PUD> {
+  fn drop(&mut self) {
+    if self.level {
+      self.pin.high();
+    } else {
+      self.pin.low();
+    }
+  }
+}
+
+/// The register handles of a specific pin. The configuration is moved from one ``Pin`` state to
+/// the next and intentionally not ``Clone`` as a copy would allow a second live ``Pin`` driving
+/// the same hardware.
 struct PinConfig {
   pub(crate) fsel: ReadWrite<u32>,
   pub(crate) fsel_field: RegisterField<u32>,
@@ -292,6 +825,132 @@ struct PinConfig {
   pub(crate) clear: WriteOnly<u32>,
   pub(crate) level: ReadOnly<u32>,
   pub(crate) setclr_val: u32,
-  pub(crate) pudclk: ReadWrite<u32>,
   pub(crate) pud_val: u32,
+  claim: PinClaim,
+}
+
+/// Releases the pin once the configuration is dropped together with the last state of the ``Pin``
+struct PinClaim(u32);
+
+impl PinClaim {
+  fn new(num: u32) -> Self {
+    crate::claim_pin(num);
+    PinClaim(num)
+  }
+}
+
+impl Drop for PinClaim {
+  fn drop(&mut self) {
+    crate::release_dropped_pin(self.0);
+  }
+}
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use super::*;
+  use crate::mock;
+
+  #[test]
+  fn input_level_readers_agree() {
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    for &num in [17, 40].iter() {
+      let pin = gpio.get_pin(num).unwrap().into_input();
+      // the levels of the neighbouring pins do not leak into the pin
+      mock::set_level(num - 1, true);
+      mock::set_level(num + 1, true);
+      assert!(pin.is_low());
+      assert!(!pin.is_high());
+      assert_eq!(pin.level(), Level::Low);
+      mock::set_level(num, true);
+      assert!(pin.is_high());
+      assert!(!pin.is_low());
+      assert_eq!(pin.level(), Level::High);
+    }
+  }
+
+  /// 32 bit time source starting shortly before its counter wraps around and advancing 1µs with each reading. It
+  /// drives GPIO 24 high while the reading is within the given range to simulate a pulse.
+  struct WrappingTimer {
+    readings: Cell<u64>,
+    pulse: core::ops::Range<u64>,
+  }
+
+  impl TimeSource for WrappingTimer {
+    const COUNTER_BITS: u32 = 32;
+
+    fn now_us(&self) -> u64 {
+      let reading = self.readings.get();
+      self.readings.set(reading + 1);
+      mock::set_level(24, self.pulse.contains(&reading));
+      (0xFFFF_FFFA + reading) & 0xFFFF_FFFF
+    }
+  }
+
+  #[test]
+  fn pulse_width_is_measured_across_the_wrap_around_of_the_timer() {
+    let _test = crate::exclusive_test();
+    let echo = crate::Gpio::new().get_pin(24).unwrap().into_input();
+    let timer = WrappingTimer {
+      readings: Cell::new(0),
+      pulse: 3..13,
+    };
+    // the pulse starts at the 5th reading and ends with the 14th reading
+    assert_eq!(echo.measure_pulse_width(true, &timer, 100), Some(9));
+  }
+
+  #[test]
+  fn edge_timeout_passes_across_the_wrap_around_of_the_timer() {
+    let _test = crate::exclusive_test();
+    let ready = crate::Gpio::new().get_pin(24).unwrap().into_input();
+    let timer = WrappingTimer {
+      readings: Cell::new(0),
+      pulse: 0..0,
+    };
+    let result = ready.wait_for_edge_timeout(GpioEvent::RisingEdge, &timer, 10);
+    assert_eq!(result, Err(GpioError::Timeout(24)));
+    // the start and 11 readings, one beyond the timeout
+    assert_eq!(timer.readings.get(), 12);
+  }
+
+  #[test]
+  fn output_switched_behind_the_handle_is_not_driven() {
+    let _test = crate::exclusive_test();
+    let led = crate::Gpio::new().get_pin(17).unwrap().into_output();
+    // another driver switches the pin into an input
+    set_function(17, Function::Input);
+    let error = Err(GpioError::InvalidFunction(17));
+    assert_eq!(led.try_high(), error);
+    assert_eq!(led.try_low(), error);
+    assert_eq!(led.try_toggle(), error);
+    #[cfg(feature = "paranoid")]
+    {
+      led.high();
+      led.toggle();
+    }
+    assert!(mock::writes_to(GPIO_BASE + 0x1C).is_empty());
+    assert!(mock::writes_to(GPIO_BASE + 0x28).is_empty());
+
+    set_function(17, Function::Output);
+    assert_eq!(led.try_toggle(), Ok(()));
+    assert_eq!(led.try_low(), Ok(()));
+    assert_eq!(mock::pin_history(17), [true, false]);
+  }
+
+  #[test]
+  fn alt_function_with_pull_up_keeps_the_pull_in_its_type() {
+    let _test = crate::exclusive_test();
+    #[cfg(not(feature = "ruspiro_pi4"))]
+    let pull_register = GPIO_BASE + 0x94;
+    #[cfg(feature = "ruspiro_pi4")]
+    let pull_register = GPIO_BASE + 0xE4;
+    let pin = crate::Gpio::new().get_pin(2).unwrap();
+    let sda: Pin<function::AltUnknown, pud::PullUp> = pin.into_alt_with_pud_up(AltFunction::Alt0);
+    assert_eq!(sda.alt_function(), AltFunction::Alt0);
+    // the pull is applied before the function is switched
+    let writes = mock::writes();
+    let pull = writes.iter().position(|&(addr, _)| addr == pull_register);
+    let fsel = writes.iter().position(|&(addr, _)| addr == GPIO_BASE);
+    assert!(pull.unwrap() < fsel.unwrap());
+  }
 }
@@ -0,0 +1,133 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Pin group
+//!
+//! A [PinGroup] is an ordered set of pins of the same GPIO bank that represent the bits of a value, e.g. the data
+//! lines of a parallel bus. Other than with a [Port](crate::Port) the pins do not need to be adjacent within the bank,
+//! the value is packed with the first pin of the group being bit ``0``. All pins are sampled with a single read of the
+//! level register and driven with a single write to the set and clear register each.
+//!
+
+use crate::interface::*;
+use crate::{pin_masks, Gpio, GpioError};
+
+/// An ordered set of pins of the same GPIO bank. The first pin of the group represents bit ``0`` of the values read
+/// and written. Like a [Pin](crate::Pin) its pins are released once dropped.
+pub struct PinGroup<const N: usize> {
+  bank: GpioBank,
+  pins: [u32; N],
+}
+
+impl Gpio {
+  /// Get a [PinGroup] of the given pins. The function of the pins is not changed, use [PinGroup::enable_output] or
+  /// [PinGroup::enable_input] to configure them.
+  /// Returns an Err if any of the pins is already in use, reserved, given more than once or does not belong to the
+  /// same GPIO bank as the first pin, in this case no pin is acquired.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let bus = GPIO
+  ///     .with_mut(|gpio| gpio.get_pin_group([5, 6, 13, 19, 26, 12, 16, 20]))
+  ///     .unwrap();
+  /// bus.enable_input();
+  /// // sample all 8 data lines at once
+  /// let data = bus.read() as u8;
+  /// # }
+  /// ```
+  pub fn get_pin_group<const N: usize>(
+    &mut self,
+    pins: [u32; N],
+  ) -> Result<PinGroup<N>, GpioError> {
+    let masks = pin_masks(&pins)?;
+    let bank = pins.first().map_or(GpioBank::Bank0, |&num| pin_bank(num));
+    if let Some(&num) = pins
+      .iter()
+      .find(|&&num| pin_bank(num) as u32 != bank as u32)
+    {
+      return Err(GpioError::BankMismatch(num));
+    }
+    self.acquire_all(masks)?;
+    for &num in pins.iter() {
+      crate::claim_pin(num);
+    }
+
+    Ok(PinGroup { bank, pins })
+  }
+
+  /// Release all pins of the [PinGroup] to allow re-usage
+  pub fn free_pin_group<const N: usize>(&mut self, group: PinGroup<N>) {
+    for &num in group.pins.iter() {
+      crate::unclaim_pin(num);
+      let _ = self.free_pin(num);
+    }
+    // the pins are released already
+    core::mem::forget(group);
+  }
+}
+
+impl<const N: usize> PinGroup<N> {
+  /// The GPIO bank the pins of this group belong to
+  pub fn bank(&self) -> GpioBank {
+    self.bank
+  }
+
+  /// The numbers of the pins of this group in the order of the bits they represent
+  pub fn pins(&self) -> &[u32; N] {
+    &self.pins
+  }
+
+  /// Switch all pins of the group into output pins
+  pub fn enable_output(&self) {
+    for &num in self.pins.iter() {
+      set_function(num, Function::Output);
+    }
+  }
+
+  /// Switch all pins of the group into input pins
+  pub fn enable_input(&self) {
+    for &num in self.pins.iter() {
+      set_function(num, Function::Input);
+    }
+  }
+
+  /// Read the level of all pins of the group with a single register read. Bit ``n`` of the returned value is the level
+  /// of the ``n``-th pin of the group.
+  pub fn read(&self) -> u32 {
+    let levels = get_levels(self.bank);
+    self.pins.iter().enumerate().fold(0, |value, (bit, &num)| {
+      value | ((levels >> (num & 31)) & 1) << bit
+    })
+  }
+
+  /// Drive the pins of the group to the levels given by the value with a single write to the set and the clear
+  /// register. Bit ``n`` of the value is the level of the ``n``-th pin of the group, bits beyond the number of pins
+  /// are ignored.
+  pub fn write(&self, value: u32) {
+    let (set, clear) = self
+      .pins
+      .iter()
+      .enumerate()
+      .fold((0, 0), |(set, clear), (bit, &num)| {
+        if bit < 32 && value & (1 << bit) != 0 {
+          (set | 1 << (num & 31), clear)
+        } else {
+          (set, clear | 1 << (num & 31))
+        }
+      });
+    set_pins(self.bank, set);
+    clear_pins(self.bank, clear);
+  }
+}
+
+impl<const N: usize> Drop for PinGroup<N> {
+  fn drop(&mut self) {
+    for &num in self.pins.iter() {
+      crate::release_dropped_pin(num);
+    }
+  }
+}
@@ -0,0 +1,98 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Split the GPIO into individual pins
+//!
+//! Instead of requesting each pin at runtime with [Gpio::get_pin] the whole GPIO could be split into one owned
+//! [Pin] per GPIO number. The exclusive ownership of each pin is then guaranteed by the type system at compile time.
+//!
+
+use crate::{function, pud, Gpio, GpioError, Pin};
+
+macro_rules! define_pins {
+  ($($field:ident: $num:expr),* $(,)?) => {
+    /// All pins of the GPIO peripheral, each pin is an individual field of this struct. The field of a pin
+    /// reserved with [Gpio::mark_reserved] is ``None``.
+    pub struct Pins {
+      $(
+        #[doc = concat!("GPIO ", stringify!($num))]
+        pub $field: Option<Pin<function::Unknown, pud::Unknown>>,
+      )*
+    }
+
+    impl Gpio {
+      /// Split the GPIO into individual owned pins. This is only possible as long as no pin is in use. Once split
+      /// all pins are marked as being in use and can no longer be requested with [Gpio::get_pin]. Pins reserved
+      /// with [Gpio::mark_reserved], e.g. the ones used by the firmware, are skipped and stay reserved.
+      /// # Example
+      /// ```no_run
+      /// # use ruspiro_gpio::*;
+      /// # fn doc() {
+      /// let pins = GPIO.with_mut(|gpio| gpio.split()).unwrap();
+      /// let led = pins.gpio17.unwrap().into_output();
+      /// led.high();
+      /// # }
+      /// ```
+      pub fn split(&mut self) -> Result<Pins, GpioError> {
+        for num in 0..self.used_pins.len() as u32 {
+          match self.check_available(num) {
+            Ok(()) | Err(GpioError::Reserved(_)) => (),
+            Err(error) => return Err(error),
+          }
+        }
+        let reserved = self.reserved_pins;
+        for (used, reserved) in self.used_pins.iter_mut().zip(reserved.iter()) {
+          *used = !reserved;
+        }
+
+        Ok(Pins {
+          $(
+            $field: if reserved[$num] {
+              None
+            } else {
+              Some(Pin::<function::Unknown, pud::Unknown>::new($num))
+            },
+          )*
+        })
+      }
+    }
+  };
+}
+
+define_pins! {
+  gpio0: 0, gpio1: 1, gpio2: 2, gpio3: 3, gpio4: 4, gpio5: 5, gpio6: 6, gpio7: 7, gpio8: 8, gpio9: 9,
+  gpio10: 10, gpio11: 11, gpio12: 12, gpio13: 13, gpio14: 14, gpio15: 15, gpio16: 16, gpio17: 17, gpio18: 18,
+  gpio19: 19, gpio20: 20, gpio21: 21, gpio22: 22, gpio23: 23, gpio24: 24, gpio25: 25, gpio26: 26, gpio27: 27,
+  gpio28: 28, gpio29: 29, gpio30: 30, gpio31: 31, gpio32: 32, gpio33: 33, gpio34: 34, gpio35: 35, gpio36: 36,
+  gpio37: 37, gpio38: 38, gpio39: 39, gpio40: 40, gpio41: 41, gpio42: 42, gpio43: 43, gpio44: 44, gpio45: 45,
+  gpio46: 46, gpio47: 47, gpio48: 48, gpio49: 49, gpio50: 50, gpio51: 51, gpio52: 52, gpio53: 53,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reserved_pins_are_skipped() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    gpio.mark_reserved(34).unwrap();
+    let pins = gpio.split().ok().unwrap();
+    assert!(pins.gpio34.is_none());
+    assert_eq!(pins.gpio17.as_ref().map(|pin| pin.num), Some(17));
+    assert_eq!(gpio.get_pin(17).err(), Some(GpioError::AlreadyInUse(17)));
+    assert_eq!(gpio.get_pin(34).err(), Some(GpioError::Reserved(34)));
+  }
+
+  #[test]
+  fn pin_in_use_prevents_the_split() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    let _pin = gpio.get_pin(17).unwrap();
+    assert_eq!(gpio.split().err(), Some(GpioError::AlreadyInUse(17)));
+    assert!(!gpio.is_pin_used(18));
+  }
+}
@@ -0,0 +1,138 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Logical pin polarity
+//!
+//! Buttons or LEDs are often wired active-low, meaning the electrical low level represents the logical active state.
+//! Wrapping a [Pin] into a [LogicalPin] with the appropriate [Polarity] allows the application code to deal with the
+//! logical state only, instead of scattering level inversions all over the place.
+//!
+
+use crate::{function, Pin};
+use core::marker::PhantomData;
+
+/// The polarity of a [LogicalPin] mapping the logical active state to the electrical level
+pub trait Polarity {
+  /// ``true`` if the low level represents the active state
+  const ACTIVE_LOW: bool;
+}
+
+/// Polarity where the high level represents the active state
+pub struct ActiveHigh;
+
+impl Polarity for ActiveHigh {
+  const ACTIVE_LOW: bool = false;
+}
+
+/// Polarity where the low level represents the active state
+pub struct ActiveLow;
+
+impl Polarity for ActiveLow {
+  const ACTIVE_LOW: bool = true;
+}
+
+/// A [Pin] with a defined [Polarity], translating the logical active state into the electrical level
+pub struct LogicalPin<POL, FUNC, PUD> {
+  pin: Pin<FUNC, PUD>,
+  _polarity: PhantomData<POL>,
+}
+
+impl<FUNC, PUD> Pin<FUNC, PUD> {
+  /// Wrap the pin into a [LogicalPin] where the high level represents the active state
+  pub fn into_active_high(self) -> LogicalPin<ActiveHigh, FUNC, PUD> {
+    LogicalPin {
+      pin: self,
+      _polarity: PhantomData,
+    }
+  }
+
+  /// Wrap the pin into a [LogicalPin] where the low level represents the active state
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let button = GPIO.with_mut(|gpio| gpio.get_pin(12).unwrap().into_input().into_pud_up().into_active_low());
+  /// if button.is_active() {
+  ///     println!("button pressed");
+  /// }
+  /// # }
+  /// ```
+  pub fn into_active_low(self) -> LogicalPin<ActiveLow, FUNC, PUD> {
+    LogicalPin {
+      pin: self,
+      _polarity: PhantomData,
+    }
+  }
+}
+
+/// Functions available for any kind of logical pin
+impl<POL: Polarity, FUNC, PUD> LogicalPin<POL, FUNC, PUD> {
+  /// Returns whether the pin is currently in the logical active state
+  pub fn is_active(&self) -> bool {
+    translate::<POL>(self.pin.read_level())
+  }
+
+  /// Unwrap the underlying [Pin]
+  pub fn into_inner(self) -> Pin<FUNC, PUD> {
+    self.pin
+  }
+}
+
+/// Functions available only for a logical output pin
+impl<POL: Polarity, PUD> LogicalPin<POL, function::Output, PUD> {
+  /// Drive the pin into the logical active (``true``) or inactive (``false``) state
+  pub fn set_active(&self, active: bool) {
+    if translate::<POL>(active) {
+      self.pin.high();
+    } else {
+      self.pin.low();
+    }
+  }
+
+  /// Drive the pin into the logical active state
+  pub fn activate(&self) {
+    self.set_active(true);
+  }
+
+  /// Drive the pin into the logical inactive state
+  pub fn deactivate(&self) {
+    self.set_active(false);
+  }
+}
+
+/// Translate between the logical active state and the electrical level of the polarity. As the
+/// translation is an inversion or not it works in both directions.
+fn translate<POL: Polarity>(state: bool) -> bool {
+  state != POL::ACTIVE_LOW
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn active_high_keeps_the_level() {
+    assert!(translate::<ActiveHigh>(true));
+    assert!(!translate::<ActiveHigh>(false));
+  }
+
+  #[test]
+  fn active_low_inverts_the_level() {
+    assert!(!translate::<ActiveLow>(true));
+    assert!(translate::<ActiveLow>(false));
+  }
+
+  #[test]
+  fn translation_is_reversible() {
+    for &state in [false, true].iter() {
+      assert_eq!(translate::<ActiveLow>(translate::<ActiveLow>(state)), state);
+      assert_eq!(
+        translate::<ActiveHigh>(translate::<ActiveHigh>(state)),
+        state
+      );
+    }
+  }
+}
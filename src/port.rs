@@ -0,0 +1,214 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # GPIO Port
+//!
+//! A [Port] is a set of pins of the same GPIO bank that are driven together. As the set and clear registers of the
+//! GPIO are bit mask registers, all pins of a port can be driven with a single register write. This avoids the skew
+//! between the lines of a parallel bus that would occur when driving the pins one by one.
+//!
+
+use crate::interface::*;
+use crate::time::Delay;
+use crate::{validate_pin, Gpio, GpioError};
+
+/// A set of pins of the same GPIO bank that are driven together. The pins are given by a bit mask where each bit
+/// represents the pin at this position within the bank.
+/// Like a [Pin](crate::Pin) its pins are released once dropped.
+pub struct Port {
+  bank: GpioBank,
+  mask: u32,
+}
+
+impl Gpio {
+  /// Get a [Port] containing all the pins of the bank given by the bit mask. The function of the pins is not
+  /// changed, use [Port::enable_output] to switch them into output pins.
+  /// Returns an Err if any of the pins is already in use or reserved, in this case no pin is acquired.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// // acquire the pins 4, 5, 6 and 7 of bank 0
+  /// let port = GPIO.with_mut(|gpio| gpio.get_port(GpioBank::Bank0, 0xF0)).unwrap();
+  /// port.enable_output();
+  /// port.write(0x50);
+  /// # }
+  /// ```
+  pub fn get_port(&mut self, bank: GpioBank, mask: u32) -> Result<Port, GpioError> {
+    match bank {
+      GpioBank::Bank0 => self.acquire_all([mask, 0])?,
+      GpioBank::Bank1 => self.acquire_all([0, mask])?,
+    }
+    BankPins::new(mask, bank).for_each(crate::claim_pin);
+
+    Ok(Port { bank, mask })
+  }
+
+  /// Release all pins of the [Port] to allow re-usage
+  pub fn free_port(&mut self, port: Port) {
+    for num in BankPins::new(port.mask, port.bank) {
+      crate::unclaim_pin(num);
+      let _ = self.free_pin(num);
+    }
+    // the pins are released already
+    core::mem::forget(port);
+  }
+
+  /// Drive all output pins of the bank given by the bit mask high with a single register write.
+  /// Returns an Err if any of the pins is not in use or not configured as output, in this case no
+  /// pin is driven.
+  /// **HINT*: The level last driven that is tracked by the output [Pin](crate::Pin) is not updated.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let d0 = gpio.get_pin(4).unwrap().into_output();
+  ///     let d1 = gpio.get_pin(5).unwrap().into_output();
+  ///     // drive both lines high at the same time
+  ///     gpio.set_pins(GpioBank::Bank0, 1 << 4 | 1 << 5).unwrap();
+  /// });
+  /// # }
+  /// ```
+  pub fn set_pins(&mut self, bank: GpioBank, mask: u32) -> Result<(), GpioError> {
+    self.check_outputs(bank, mask)?;
+    set_pins(bank, mask);
+    Ok(())
+  }
+
+  /// Drive all output pins of the bank given by the bit mask low with a single register write.
+  /// Returns an Err if any of the pins is not in use or not configured as output, in this case no
+  /// pin is driven.
+  /// **HINT*: The level last driven that is tracked by the output [Pin](crate::Pin) is not updated.
+  pub fn clear_pins(&mut self, bank: GpioBank, mask: u32) -> Result<(), GpioError> {
+    self.check_outputs(bank, mask)?;
+    clear_pins(bank, mask);
+    Ok(())
+  }
+
+  /// Check all pins of the bank given by the bit mask are in use and configured as output
+  pub(crate) fn check_outputs(&mut self, bank: GpioBank, mask: u32) -> Result<(), GpioError> {
+    self.reclaim_dropped_pins();
+    for num in BankPins::new(mask, bank) {
+      validate_pin(num)?;
+      if !self.used_pins.get(num as usize).copied().unwrap_or(false) {
+        return Err(GpioError::NotInUse(num));
+      }
+      if get_function(num) != Function::Output {
+        return Err(GpioError::InvalidFunction(num));
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Port {
+  /// The GPIO bank the pins of this port belong to
+  pub fn bank(&self) -> GpioBank {
+    self.bank
+  }
+
+  /// The bit mask of the pins of this port
+  pub fn mask(&self) -> u32 {
+    self.mask
+  }
+
+  /// Switch all pins of the port into output pins
+  pub fn enable_output(&self) {
+    for num in BankPins::new(self.mask, self.bank) {
+      set_function(num, Function::Output);
+    }
+  }
+
+  /// Stage the output level of all pins of the port given by the value and only afterwards switch them into output
+  /// pins. As the output level is latched while a pin is not an output, every pin starts driving its final level
+  /// once it becomes an output. This avoids a transient wrong value on a parallel bus while initializing it.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let port = GPIO.with_mut(|gpio| gpio.get_port(GpioBank::Bank0, 0xF0)).unwrap();
+  /// port.write_then_enable(0x50);
+  /// # }
+  /// ```
+  pub fn write_then_enable(&self, value: u32) {
+    // the level needs to be written before any function select is touched
+    self.write(value);
+    self.enable_output();
+  }
+
+  /// Drive the pins of the port given by the bit mask high. Bits of pins not belonging to the port are ignored.
+  pub fn set(&self, mask: u32) {
+    set_pins(self.bank, mask & self.mask);
+  }
+
+  /// Drive the pins of the port given by the bit mask low. Bits of pins not belonging to the port are ignored.
+  pub fn clear(&self, mask: u32) {
+    clear_pins(self.bank, mask & self.mask);
+  }
+
+  /// Drive all pins of the port to the level given by the corresponding bit of the value
+  pub fn write(&self, value: u32) {
+    self.set(value);
+    self.clear(!value);
+  }
+
+  /// Read the level of all pins of the port. Bits of pins not belonging to the port are always ``0``.
+  pub fn read(&self) -> u32 {
+    get_levels(self.bank) & self.mask
+  }
+
+  /// Apply a sequence of pin changes in the given order. Each step is a bit mask of the pins to change and whether
+  /// they should be driven high (``true``) or low (``false``). After each step the given delay is applied. This
+  /// captures the common "set data, delay, pulse enable, delay" pattern of parallel interfaces.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # struct Timer;
+  /// # impl Delay for Timer { fn delay_us(&self, _: u32) {} }
+  /// # fn doc() {
+  /// let port = GPIO.with_mut(|gpio| gpio.get_port(GpioBank::Bank0, 0x1F0)).unwrap();
+  /// port.enable_output();
+  /// // put data on pins 4..7, then pulse the enable line at pin 8
+  /// port.write_sequence(&[(0x50, true), (0xA0, false), (0x100, true), (0x100, false)], &Timer, 1);
+  /// # }
+  /// ```
+  pub fn write_sequence(&self, steps: &[(u32, bool)], delay: &impl Delay, step_delay_us: u32) {
+    for &(mask, set) in steps {
+      if set {
+        self.set(mask);
+      } else {
+        self.clear(mask);
+      }
+      delay.delay_us(step_delay_us);
+    }
+  }
+}
+
+impl Drop for Port {
+  fn drop(&mut self) {
+    BankPins::new(self.mask, self.bank).for_each(crate::release_dropped_pin);
+  }
+}
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use super::*;
+  use crate::mock;
+
+  #[test]
+  fn write_then_enable_stages_the_levels_before_the_function_select() {
+    let _test = crate::exclusive_test();
+    let port = Gpio::new().get_port(GpioBank::Bank0, 0xF0).unwrap();
+    port.write_then_enable(0x50);
+    let writes = mock::writes();
+    let (gpset0, gpclr0) = (mock::GPIO_BASE + 0x1C, mock::GPIO_BASE + 0x28);
+    assert_eq!(writes[..2], [(gpset0, 0x50), (gpclr0, 0xA0)]);
+    // all remaining writes are function selects switching the pins 4..7 into outputs
+    assert!(writes[2..].iter().all(|&(addr, _)| addr == mock::GPIO_BASE));
+    assert_eq!(mock::peek(mock::GPIO_BASE), 0o1111 << 12);
+  }
+}
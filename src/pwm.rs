@@ -0,0 +1,242 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Software PWM
+//!
+//! An output pin could be driven with a pulse width modulated signal without the PWM peripheral. The pin is toggled
+//! from within the interrupt of the compare channel 3 of the system timer, one of the two channels not used by the
+//! GPU. So there can only be one software PWM pin at any time.
+//!
+//! The system timer runs at 1MHz, so the high and low phases of the signal are a multiple of 1µs. The duty cycle is
+//! given in 256 steps, but with a period below 255µs not every step results in a different high phase. Each phase is
+//! at least [MIN_PHASE_US] long to not miss the next timer compare while the interrupt is handled, so the duty cycles
+//! close to ``0`` and ``255`` are less accurate for short periods. The interrupt latency adds jitter of some µs to
+//! each edge. This is fine to dim a LED or to drive a hobby servo with a 20ms period, but not for high frequency
+//! signals.
+//!
+
+use crate::interface::*;
+#[cfg(feature = "mock-mmio")]
+use crate::mock::irq;
+use crate::{function, GpioError, Pin};
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+#[cfg(not(feature = "mock-mmio"))]
+use ruspiro_interrupt as irq;
+use ruspiro_interrupt::{Interrupt, IrqHandler, IsrSender};
+
+/// The minimum length of the high and low phase of the software PWM in micro seconds
+pub const MIN_PHASE_US: u32 = 5;
+
+/// marker of no pin being driven as software PWM
+const NO_PIN: u32 = u32::MAX;
+
+/// the pin currently driven as software PWM
+static PWM_PIN: AtomicU32 = AtomicU32::new(NO_PIN);
+/// the period of the software PWM in micro seconds
+static PWM_PERIOD: AtomicU32 = AtomicU32::new(0);
+/// the duty cycle of the software PWM in 1/255 of the period
+static PWM_DUTY: AtomicU32 = AtomicU32::new(0);
+/// whether the software PWM pin is currently in its high phase
+static PWM_HIGH: AtomicBool = AtomicBool::new(false);
+
+/// An output [Pin] driven with a pulse width modulated signal from the system timer interrupt. The pin is driven
+/// low and the timer interrupt is deactivated once the ``PwmPin`` is dropped.
+pub struct PwmPin<PUD> {
+  pin: Option<Pin<function::Output, PUD>>,
+}
+
+impl<PUD> Pin<function::Output, PUD> {
+  /// Drive the output pin with a software PWM signal of the given period in micro seconds. The duty cycle is the
+  /// ratio of the high phase to the period, where ``0`` is always low and ``255`` is always high. The
+  /// phases are a multiple of 1µs and at least [MIN_PHASE_US] long. The interrupt latency adds some µs of jitter to
+  /// each edge.
+  /// Returns an Err with the unchanged pin if the period is shorter than two times [MIN_PHASE_US], as the signal would
+  /// not have the requested frequency, or with ``GpioError::AlreadyInUse`` if another pin is already driven as
+  /// software PWM.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let led = GPIO.with_mut(|gpio| gpio.get_pin(18)).unwrap().into_output();
+  /// // dim the LED to a quarter of its brightness with a 100Hz PWM
+  /// let mut pwm = match led.into_pwm(10_000, 64) {
+  ///     Ok(pwm) => pwm,
+  ///     Err((err, _led)) => panic!("software PWM not available: {:?}", err),
+  /// };
+  /// // and brighten it up later on
+  /// pwm.set_duty(192);
+  /// # }
+  /// ```
+  pub fn into_pwm(self, period_us: u32, duty: u8) -> Result<PwmPin<PUD>, (GpioError, Self)> {
+    if period_us < 2 * MIN_PHASE_US {
+      return Err((GpioError::InvalidPeriod(period_us), self));
+    }
+    if PWM_PIN
+      .compare_exchange(NO_PIN, self.num, Ordering::AcqRel, Ordering::Acquire)
+      .is_err()
+    {
+      return Err((GpioError::AlreadyInUse(self.num), self));
+    }
+    PWM_PERIOD.store(period_us, Ordering::Release);
+    PWM_DUTY.store(duty as u32, Ordering::Release);
+    PWM_HIGH.store(false, Ordering::Release);
+    self.low();
+    // start with the first phase right away
+    SYS_TIMER_C3::Register.set(SYS_TIMER_CLO::Register.get().wrapping_add(MIN_PHASE_US));
+    irq::activate(Interrupt::SystemTimer3, None);
+
+    Ok(PwmPin { pin: Some(self) })
+  }
+}
+
+impl<PUD> PwmPin<PUD> {
+  /// Change the duty cycle of the PWM signal. The new duty cycle takes effect with the next phase of the signal.
+  pub fn set_duty(&mut self, duty: u8) {
+    PWM_DUTY.store(duty as u32, Ordering::Release);
+  }
+
+  /// The current duty cycle of the PWM signal
+  pub fn duty(&self) -> u8 {
+    PWM_DUTY.load(Ordering::Acquire) as u8
+  }
+
+  /// The period of the PWM signal in micro seconds
+  pub fn period_us(&self) -> u32 {
+    PWM_PERIOD.load(Ordering::Acquire)
+  }
+
+  /// Stop the PWM signal and return the output pin. The pin is driven low.
+  pub fn into_inner(mut self) -> Pin<function::Output, PUD> {
+    self.stop();
+    // the pin is always present until the PwmPin is consumed or dropped
+    self.pin.take().unwrap()
+  }
+
+  fn stop(&mut self) {
+    irq::deactivate(Interrupt::SystemTimer3);
+    PWM_PIN.store(NO_PIN, Ordering::Release);
+    if let Some(pin) = &self.pin {
+      pin.low();
+    }
+  }
+}
+
+impl<PUD> Drop for PwmPin<PUD> {
+  fn drop(&mut self) {
+    if self.pin.is_some() {
+      self.stop();
+    }
+  }
+}
+
+/// Drive the next phase of the software PWM signal and schedule the timer compare for the following one
+fn process_timer_interrupt() {
+  // acknowledge the compare match of channel 3
+  SYS_TIMER_CS::Register.set(1 << 3);
+  let num = PWM_PIN.load(Ordering::Acquire);
+  if num == NO_PIN {
+    return;
+  }
+  let (level, phase) = next_phase(
+    PWM_PERIOD.load(Ordering::Acquire),
+    PWM_DUTY.load(Ordering::Acquire),
+    PWM_HIGH.load(Ordering::Acquire),
+  );
+  let mask = 1 << (num & 31);
+  if level {
+    set_pins(pin_bank(num), mask);
+  } else {
+    clear_pins(pin_bank(num), mask);
+  }
+  PWM_HIGH.store(level, Ordering::Release);
+  SYS_TIMER_C3::Register.set(SYS_TIMER_CLO::Register.get().wrapping_add(phase));
+}
+
+/// The level of the next phase of the software PWM signal and its length in micro seconds, based on the level of
+/// the current phase. The phase is at least [MIN_PHASE_US] long.
+fn next_phase(period: u32, duty: u32, high: bool) -> (bool, u32) {
+  let high_time = (period as u64 * duty as u64 / 255) as u32;
+  let (level, phase) = match duty {
+    0 => (false, period),
+    255 => (true, period),
+    _ if high => (false, period - high_time),
+    _ => (true, high_time),
+  };
+  (level, phase.max(MIN_PHASE_US))
+}
+
+/// Implement the interrupt handler of the system timer compare channel 3 driving the software PWM
+/// # Safety
+/// The handler only accesses the atomic state of the software PWM and the registers of its pin.
+#[IrqHandler(SystemTimer3)]
+unsafe fn handle_soft_pwm(tx: Option<IsrSender<Box<dyn Any>>>) {
+  process_timer_interrupt();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn duty_0_stays_low() {
+    assert_eq!(next_phase(10_000, 0, false), (false, 10_000));
+    assert_eq!(next_phase(10_000, 0, true), (false, 10_000));
+  }
+
+  #[test]
+  fn duty_255_stays_high() {
+    assert_eq!(next_phase(10_000, 255, false), (true, 10_000));
+    assert_eq!(next_phase(10_000, 255, true), (true, 10_000));
+  }
+
+  #[test]
+  fn duty_1_has_short_high_phase() {
+    assert_eq!(next_phase(10_000, 1, false), (true, 39));
+    assert_eq!(next_phase(10_000, 1, true), (false, 9_961));
+  }
+
+  #[test]
+  fn duty_254_has_short_low_phase() {
+    assert_eq!(next_phase(10_000, 254, false), (true, 9_960));
+    assert_eq!(next_phase(10_000, 254, true), (false, 40));
+  }
+
+  #[test]
+  fn phases_are_at_least_min_phase() {
+    assert_eq!(next_phase(100, 1, false), (true, MIN_PHASE_US));
+    assert_eq!(next_phase(100, 254, true), (false, MIN_PHASE_US));
+  }
+
+  #[test]
+  #[cfg(feature = "mock-mmio")]
+  fn rejected_pin_is_handed_back() {
+    let _test = crate::exclusive_test();
+    let mut gpio = crate::Gpio::new();
+    let pin = gpio.get_pin(18).unwrap().into_output();
+    let pin = match pin.into_pwm(2 * MIN_PHASE_US - 1, 64) {
+      Err((GpioError::InvalidPeriod(_), pin)) => pin,
+      _ => panic!("period shorter than two phases accepted"),
+    };
+    let pwm = pin.into_pwm(10_000, 64).ok().unwrap();
+    assert!(crate::mock::is_irq_active(Interrupt::SystemTimer3));
+
+    // the error names the pin requested, not the one already driven as software PWM
+    let other = gpio.get_pin(19).unwrap().into_output();
+    let other = match other.into_pwm(10_000, 64) {
+      Err((GpioError::AlreadyInUse(num), other)) => {
+        assert_eq!(num, 19);
+        other
+      }
+      _ => panic!("second software PWM pin accepted"),
+    };
+    other.high();
+
+    drop(pwm);
+    assert!(!crate::mock::is_irq_active(Interrupt::SystemTimer3));
+    assert!(other.into_pwm(10_000, 64).is_ok());
+  }
+}
@@ -0,0 +1,72 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Timing abstractions
+//!
+//! The GPIO peripheral has no notion of time. Functions that require a delay between pin changes take an
+//! implementation of the traits provided here, so the time source (e.g. the system timer) can be choosen by the user.
+//!
+
+/// A blocking delay
+pub trait Delay {
+  /// Block for (at least) the given number of micro seconds
+  fn delay_us(&self, us: u32);
+}
+
+/// A monotonic time source. The time counts up in micro seconds and wraps around to ``0`` once it exceeds the width of
+/// the counter behind it, e.g. after about 71 minutes for the 32 bit counter of the system timer. The time passed
+/// between two readings is always taken with [TimeSource::elapsed_us], so a wrap around in between does not matter
+/// as long as the interval is shorter than the counter range.
+pub trait TimeSource {
+  /// The width of the counter in bits. The readings of [TimeSource::now_us] wrap around within this width.
+  const COUNTER_BITS: u32 = 64;
+
+  /// The current time in micro seconds. The value is required to never decrease, except for wrapping around to ``0``
+  /// after the counter reached its maximum.
+  fn now_us(&self) -> u64;
+
+  /// The micro seconds passed from the reading ``since`` to the later reading ``now``, taking a wrap around of the
+  /// counter in between into account
+  fn elapsed_us(&self, since: u64, now: u64) -> u64 {
+    let mask = if Self::COUNTER_BITS >= 64 {
+      u64::MAX
+    } else {
+      (1 << Self::COUNTER_BITS) - 1
+    };
+    now.wrapping_sub(since) & mask
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct SystemTimer;
+
+  impl TimeSource for SystemTimer {
+    const COUNTER_BITS: u32 = 32;
+
+    fn now_us(&self) -> u64 {
+      0
+    }
+  }
+
+  struct MonotonicTimer;
+
+  impl TimeSource for MonotonicTimer {
+    fn now_us(&self) -> u64 {
+      0
+    }
+  }
+
+  #[test]
+  fn elapsed_time_spans_the_wrap_around_of_the_counter() {
+    assert_eq!(SystemTimer.elapsed_us(100, 150), 50);
+    assert_eq!(SystemTimer.elapsed_us(0xFFFF_FFF0, 0x10), 0x20);
+    assert_eq!(MonotonicTimer.elapsed_us(100, 150), 50);
+    assert_eq!(MonotonicTimer.elapsed_us(u64::MAX - 0xF, 0x10), 0x20);
+  }
+}
@@ -0,0 +1,168 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # GPIO event trace
+//!
+//! Diagnostic log of the most recent event dispatches. It records what the interrupt handler
+//! actually saw and helps to find out why an event handler fires too often or not at all. This is
+//! only available with the ``trace-events`` feature.
+//!
+
+use crate::interface::*;
+use crate::{Gpio, GpioEvents};
+
+/// The number of dispatches kept in the event trace
+pub const EVENT_TRACE_SIZE: usize = 32;
+
+/// A single entry of the event trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventTrace {
+  /// The GPIO pin the event was detected for
+  pub pin: u32,
+  /// The detect events armed for this pin at the time of the dispatch
+  pub events: GpioEvents,
+  /// The running number of this dispatch, used to order the entries
+  pub count: u32,
+}
+
+const EMPTY_TRACE: EventTrace = EventTrace {
+  pin: 0,
+  events: GpioEvents::empty(),
+  count: 0,
+};
+
+/// Ring buffer of the most recent trace entries
+struct TraceBuffer {
+  entries: [EventTrace; EVENT_TRACE_SIZE],
+  /// The total number of entries pushed so far
+  count: u32,
+}
+
+impl TraceBuffer {
+  const fn new() -> Self {
+    Self {
+      entries: [EMPTY_TRACE; EVENT_TRACE_SIZE],
+      count: 0,
+    }
+  }
+
+  /// Add an entry, overwriting the oldest one once the buffer is full
+  fn push(&mut self, pin: u32, events: GpioEvents) {
+    let slot = self.count as usize % EVENT_TRACE_SIZE;
+    self.entries[slot] = EventTrace {
+      pin,
+      events,
+      count: self.count,
+    };
+    self.count = self.count.wrapping_add(1);
+  }
+
+  /// Copy of the entries together with the number of valid entries
+  fn snapshot(&self) -> ([EventTrace; EVENT_TRACE_SIZE], usize) {
+    (self.entries, (self.count as usize).min(EVENT_TRACE_SIZE))
+  }
+}
+
+/// The trace of the event dispatches
+static mut EVENT_TRACE: TraceBuffer = TraceBuffer::new();
+
+/// Record the dispatch of an event detected for the given pin
+/// # Safety
+/// This is only safe to be called from within the GPIO bank interrupt handler.
+pub(crate) unsafe fn record(pin: u32, bank: GpioBank) {
+  let enables = get_detect_enables(bank);
+  let bit = 1 << (pin & 31);
+  let mut events = 0;
+  for (idx, enable) in enables.iter().enumerate() {
+    if enable & bit != 0 {
+      events |= 1 << idx;
+    }
+  }
+  EVENT_TRACE.push(pin, GpioEvents::from_bits(events));
+}
+
+impl Gpio {
+  /// Get a copy of the most recent event dispatches together with the number of valid entries, at
+  /// most [EVENT_TRACE_SIZE]. Once the trace is full the oldest entry is overwritten, so the entries
+  /// are not ordered. Use the ``count`` of each entry to order them.
+  /// **HINT*: The IRQ's are masked on the current core while copying the trace. On a multi core
+  /// system call this from the core handling the GPIO interrupts, otherwise an entry could be
+  /// recorded while it is copied.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let (entries, len) = GPIO.with_mut(|gpio| gpio.recent_events());
+  /// for trace in &entries[..len] {
+  ///     println!("#{} pin {} events {:?}", trace.count, trace.pin, trace.events);
+  /// }
+  /// # }
+  /// ```
+  pub fn recent_events(&self) -> ([EventTrace; EVENT_TRACE_SIZE], usize) {
+    // the IRQ's are masked while copying, so the bank interrupt handler of this core can't write
+    // the trace in between
+    without_interrupts(|| unsafe { EVENT_TRACE.snapshot() })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn empty_trace_has_no_entries() {
+    let trace = TraceBuffer::new();
+    assert_eq!(trace.snapshot().1, 0);
+  }
+
+  #[test]
+  fn entries_are_kept_in_push_order_until_full() {
+    let mut trace = TraceBuffer::new();
+    trace.push(17, GpioEvents::RISING);
+    trace.push(40, GpioEvents::FALLING | GpioEvents::LOW);
+    let (entries, len) = trace.snapshot();
+    assert_eq!(len, 2);
+    assert_eq!(
+      entries[0],
+      EventTrace {
+        pin: 17,
+        events: GpioEvents::RISING,
+        count: 0
+      }
+    );
+    assert_eq!(
+      entries[1],
+      EventTrace {
+        pin: 40,
+        events: GpioEvents::FALLING | GpioEvents::LOW,
+        count: 1
+      }
+    );
+  }
+
+  #[test]
+  fn oldest_entries_are_overwritten_once_full() {
+    let mut trace = TraceBuffer::new();
+    let pushed = EVENT_TRACE_SIZE as u32 + 8;
+    for count in 0..pushed {
+      trace.push(count % 54, GpioEvents::HIGH);
+    }
+    let (entries, len) = trace.snapshot();
+    assert_eq!(len, EVENT_TRACE_SIZE);
+    for (slot, entry) in entries.iter().enumerate() {
+      // the first 8 slots hold the newest entries, the remaining ones the oldest still kept
+      let count = if slot < 8 {
+        EVENT_TRACE_SIZE as u32 + slot as u32
+      } else {
+        slot as u32
+      };
+      assert_eq!(entry.count, count);
+      assert_eq!(entry.pin, count % 54);
+    }
+    let oldest = entries.iter().map(|entry| entry.count).min();
+    assert_eq!(oldest, Some(pushed - EVENT_TRACE_SIZE as u32));
+  }
+}
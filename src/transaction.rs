@@ -0,0 +1,198 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # GPIO transaction
+//!
+//! Batch several pin changes and apply them together. The changes are recorded first and grouped by register, so
+//! each register is written at most once when the transaction is applied.
+//!
+
+use crate::interface::*;
+use crate::{validate_pin, Gpio, GpioError};
+
+/// Builder recording the pin changes of a transaction. See [Gpio::transaction].
+pub struct TxBuilder {
+  fsel_masks: [u32; 6],
+  fsel_values: [u32; 6],
+  set: [u32; 2],
+  clear: [u32; 2],
+  /// bank masks of the pins per pull up/down setting
+  pud: [[u32; 2]; 3],
+  error: Option<GpioError>,
+}
+
+impl TxBuilder {
+  const fn new() -> Self {
+    Self {
+      fsel_masks: [0; 6],
+      fsel_values: [0; 6],
+      set: [0; 2],
+      clear: [0; 2],
+      pud: [[0; 2]; 3],
+      error: None,
+    }
+  }
+
+  /// Validate the pin number and return its bank index and bit mask
+  fn pin(&mut self, num: u32) -> Option<(usize, u32)> {
+    if let Err(error) = validate_pin(num) {
+      self.error.get_or_insert(error);
+      return None;
+    }
+    Some(((num / 32) as usize, 1 << (num & 31)))
+  }
+
+  /// Switch the pin to the given function
+  pub fn function(&mut self, num: u32, function: Function) -> &mut Self {
+    if self.pin(num).is_some() {
+      let (reg, shift) = gpfsel_for(num);
+      self.fsel_masks[reg] |= 0b111 << shift;
+      self.fsel_values[reg] =
+        (self.fsel_values[reg] & !(0b111 << shift)) | ((function as u32) << shift);
+    }
+    self
+  }
+
+  /// Drive the pin high
+  pub fn set(&mut self, num: u32) -> &mut Self {
+    if let Some((bank, mask)) = self.pin(num) {
+      self.set[bank] |= mask;
+      self.clear[bank] &= !mask;
+    }
+    self
+  }
+
+  /// Drive the pin low
+  pub fn clear(&mut self, num: u32) -> &mut Self {
+    if let Some((bank, mask)) = self.pin(num) {
+      self.clear[bank] |= mask;
+      self.set[bank] &= !mask;
+    }
+    self
+  }
+
+  /// Apply the pull up/down setting to the pin
+  pub fn pull(&mut self, num: u32, pud: Pud) -> &mut Self {
+    if let Some((bank, mask)) = self.pin(num) {
+      for masks in self.pud.iter_mut() {
+        masks[bank] &= !mask;
+      }
+      self.pud[pud as usize][bank] |= mask;
+    }
+    self
+  }
+
+  /// Write the recorded changes to the registers
+  fn flush(&self) {
+    // stage the output levels first, so pins switched to outputs start with the level given
+    for (idx, bank) in [GpioBank::Bank0, GpioBank::Bank1].iter().enumerate() {
+      if self.set[idx] != 0 {
+        set_pins(*bank, self.set[idx]);
+      }
+      if self.clear[idx] != 0 {
+        clear_pins(*bank, self.clear[idx]);
+      }
+    }
+    update_function_selects(self.fsel_masks, self.fsel_values);
+    for (pud, masks) in [Pud::Disabled, Pud::PullDown, Pud::PullUp]
+      .iter()
+      .zip(self.pud.iter())
+    {
+      if masks[0] != 0 || masks[1] != 0 {
+        pud_sequence(*pud, masks[0], masks[1]);
+      }
+    }
+  }
+}
+
+impl Gpio {
+  /// Record several pin changes with the builder passed to the closure and apply them together with the IRQ's masked
+  /// on the current core. The output levels are written first, followed by the function selects and the pull
+  /// up/down settings. Each register is written at most once.
+  /// Returns an Err if any of the pin numbers is out of the range of the available GPIO pins, in this case no change
+  /// is applied.
+  /// **HINT*: Only the changes that end up in the same register take effect at the same time, e.g. the levels of all
+  /// pins of a bank or the functions of up to 10 consecutive pins. Changes across registers are applied without being
+  /// interrupted but one after the other. The changes are applied to the pin numbers given regardless of who owns
+  /// the pins, so the typed state of a [Pin](crate::Pin) for the same number is not updated.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     gpio.transaction(|tx| {
+  ///         tx.clear(22).function(22, Function::Output);
+  ///         tx.set(23).function(23, Function::Output);
+  ///         tx.function(24, Function::Input).pull(24, Pud::PullUp);
+  ///     }).unwrap();
+  /// });
+  /// # }
+  /// ```
+  pub fn transaction<F: FnOnce(&mut TxBuilder)>(&mut self, f: F) -> Result<(), GpioError> {
+    let mut tx = TxBuilder::new();
+    f(&mut tx);
+    if let Some(error) = tx.error {
+      return Err(error);
+    }
+    without_interrupts(|| tx.flush());
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn levels_are_grouped_per_bank() {
+    let mut tx = TxBuilder::new();
+    tx.set(0).set(31).clear(5).set(32).clear(53);
+    assert_eq!(tx.set, [1 | 1 << 31, 1]);
+    assert_eq!(tx.clear, [1 << 5, 1 << 21]);
+    assert!(tx.error.is_none());
+  }
+
+  #[test]
+  fn last_level_of_a_pin_wins() {
+    let mut tx = TxBuilder::new();
+    tx.set(4).clear(4).clear(40).set(40);
+    assert_eq!(tx.set, [0, 1 << 8]);
+    assert_eq!(tx.clear, [1 << 4, 0]);
+  }
+
+  #[test]
+  fn functions_are_merged_per_select_register() {
+    let mut tx = TxBuilder::new();
+    tx.function(10, Function::Output)
+      .function(19, Function::Alt5)
+      .function(10, Function::Alt0);
+    assert_eq!(tx.fsel_masks, [0, 0b111 << 27 | 0b111, 0, 0, 0, 0]);
+    assert_eq!(
+      tx.fsel_values[1],
+      (Function::Alt5 as u32) << 27 | Function::Alt0 as u32
+    );
+  }
+
+  #[test]
+  fn pin_takes_only_the_last_pull_setting() {
+    let mut tx = TxBuilder::new();
+    tx.pull(2, Pud::PullUp)
+      .pull(33, Pud::PullDown)
+      .pull(2, Pud::Disabled);
+    assert_eq!(tx.pud[Pud::Disabled as usize], [1 << 2, 0]);
+    assert_eq!(tx.pud[Pud::PullDown as usize], [0, 1 << 1]);
+    assert_eq!(tx.pud[Pud::PullUp as usize], [0, 0]);
+  }
+
+  #[test]
+  fn first_invalid_pin_is_reported() {
+    let mut tx = TxBuilder::new();
+    tx.set(3).set(GPIO_PIN_COUNT).clear(GPIO_PIN_COUNT + 1);
+    assert_eq!(tx.error, Some(GpioError::OutOfRange(GPIO_PIN_COUNT)));
+    assert_eq!(tx.set, [1 << 3, 0]);
+    assert_eq!(tx.clear, [0, 0]);
+  }
+}
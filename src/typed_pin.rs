@@ -0,0 +1,134 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Typed pin
+//!
+//! A pin with its number given as const generic parameter. The bank, the bit mask and the registers to access are
+//! resolved at compile time, so driving the pin compiles down to a store of a constant to a constant address without
+//! any branch or shift. This fits bit-banging loops with a pin known while writing the code. A [Pin](crate::Pin) is
+//! still the way to go with pins only known at runtime.
+//!
+//! With a [Pin](crate::Pin) the bank and the bit mask are calculated once while the pin is created. However, each
+//! ``high`` or ``low`` loads the register address and the bit mask from the pin and records the driven level in the
+//! pin as well as in the shadow of the driven levels shared by all paths driving pins. With the ``paranoid`` feature
+//! it also verifies the function of the pin. ``TypedPin::<21>::high`` on the other hand is a single ``str`` of
+//! ``1 << 21`` to ``GPSET0`` and the update of the shared shadow with the constant bit mask.
+//!
+
+use crate::interface::*;
+use crate::{Gpio, GpioError};
+
+/// A pin with the number ``N`` known at compile time. See [Gpio::get_typed_pin].
+/// Like a [Pin](crate::Pin) it is released once dropped.
+pub struct TypedPin<const N: u32> {
+  _private: (),
+}
+
+impl<const N: u32> TypedPin<N> {
+  /// The number of the pin
+  pub const NUM: u32 = N;
+  /// The bit mask of the pin within the registers of its bank
+  pub const MASK: u32 = 1 << (N & 31);
+  /// The GPIO bank the pin belongs to
+  pub const BANK: GpioBank = if N < 32 {
+    GpioBank::Bank0
+  } else {
+    GpioBank::Bank1
+  };
+  // Verify the pin number at compile time. An out of range pin will fail the build with an index
+  // out of bounds once the pin is requested
+  const IN_RANGE: () = [()][(N >= GPIO_PIN_COUNT) as usize];
+
+  /// The number of the pin
+  pub const fn num(&self) -> u32 {
+    N
+  }
+
+  /// Switch the pin into an output pin
+  pub fn enable_output(&self) {
+    set_function(N, Function::Output);
+  }
+
+  /// Switch the pin into an input pin
+  pub fn enable_input(&self) {
+    set_function(N, Function::Input);
+  }
+
+  /// Drive the pin high.
+  /// **HINT*: The function of the pin is not verified, the pin needs to be switched into an output
+  /// pin with [TypedPin::enable_output] first.
+  pub fn high(&self) {
+    if N < 32 {
+      GPSET0::Register.set(Self::MASK);
+    } else {
+      GPSET1::Register.set(Self::MASK);
+    }
+    track_levels(Self::BANK, Self::MASK, true);
+  }
+
+  /// Drive the pin low.
+  /// **HINT*: The function of the pin is not verified, the pin needs to be switched into an output
+  /// pin with [TypedPin::enable_output] first.
+  pub fn low(&self) {
+    if N < 32 {
+      GPCLR0::Register.set(Self::MASK);
+    } else {
+      GPCLR1::Register.set(Self::MASK);
+    }
+    track_levels(Self::BANK, Self::MASK, false);
+  }
+
+  /// Read the current level of the pin, ``true`` for high and ``false`` for low
+  pub fn is_high(&self) -> bool {
+    let levels = if N < 32 {
+      GPLEV0::Register.get()
+    } else {
+      GPLEV1::Register.get()
+    };
+    levels & Self::MASK != 0
+  }
+
+  /// Consume the pin and return its number. Other than dropping the pin it stays in use, so the
+  /// number could be handed to [Gpio::free_pin] to release it.
+  pub fn into_num(self) -> u32 {
+    // skip the release of the pin on drop
+    core::mem::forget(self);
+    crate::unclaim_pin(N);
+    N
+  }
+}
+
+impl<const N: u32> Drop for TypedPin<N> {
+  fn drop(&mut self) {
+    crate::release_dropped_pin(N);
+  }
+}
+
+impl Gpio {
+  /// Get a new pin with its number known at compile time. An out of range pin number fails the
+  /// build.
+  /// Returns an Err if the pin is already in use or reserved, otherwise an Ok(TypedPin)
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let clock = GPIO.with_mut(|gpio| gpio.get_typed_pin::<21>()).unwrap();
+  /// clock.enable_output();
+  /// for _ in 0..8 {
+  ///     clock.high();
+  ///     clock.low();
+  /// }
+  /// # }
+  /// ```
+  pub fn get_typed_pin<const N: u32>(&mut self) -> Result<TypedPin<N>, GpioError> {
+    #[allow(clippy::let_unit_value)]
+    let _ = TypedPin::<N>::IN_RANGE;
+    self.check_available(N)?;
+    self.used_pins[N as usize] = true;
+    crate::claim_pin(N);
+    Ok(TypedPin { _private: () })
+  }
+}
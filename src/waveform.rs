@@ -0,0 +1,107 @@
+/***********************************************************************************************************************
+ * Copyright (c) 2021 by the authors
+ *
+ * Author: André Borrmann
+ * License: Apache License 2.0
+ **********************************************************************************************************************/
+//! # Waveform generation
+//!
+//! Generate waveforms on output pins with busy waiting delays. The IRQ's are masked while the waveform is generated to
+//! keep the jitter as low as possible. This is intended for test clocks or simple tone output where software PWM is
+//! not accurate enough.
+//!
+
+use crate::interface::*;
+use crate::{function, Gpio, Pin};
+
+impl Gpio {
+  /// Generate a square wave on the output pin. The ``half_period_cycles`` give the length of half a period in CPU
+  /// cycles, the ``duty`` the percentage (0..100) of the full period the pin is driven high. The waveform is generated
+  /// for the given number of periods, afterwards the pin is driven low.
+  /// **HINT*: This blocks until the waveform has been generated and masks the IRQ's on the current core while doing
+  /// so. The register writes add a small constant overhead to each half of the period.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// GPIO.with_mut(|gpio| {
+  ///     let pin = gpio.get_pin(18).unwrap().into_output();
+  ///     // 1000 periods with 25% duty cycle
+  ///     gpio.square_wave(&pin, 500, 1000, 25);
+  /// });
+  /// # }
+  /// ```
+  pub fn square_wave<PUD>(
+    &self,
+    pin: &Pin<function::Output, PUD>,
+    half_period_cycles: u32,
+    cycles: u32,
+    duty: u8,
+  ) {
+    let period = half_period_cycles.saturating_mul(2);
+    let high_cycles = (period as u64 * duty.min(100) as u64 / 100) as u32;
+    let low_cycles = period - high_cycles;
+    without_interrupts(|| {
+      for _ in 0..cycles {
+        pin.high();
+        wait_cycles(high_cycles);
+        pin.low();
+        wait_cycles(low_cycles);
+      }
+    });
+  }
+}
+
+impl<PUD> Pin<function::Output, PUD> {
+  /// Emit the bit pattern on the output pin. Each bit is driven for ``half_period_cycles`` CPU cycles, ``true`` as high
+  /// and ``false`` as low level. Afterwards the pin keeps the level of the last bit.
+  /// **HINT*: This blocks until the pattern has been emitted and masks the IRQ's on the current core while doing so.
+  /// Each bit requires a write to the set or clear register of the GPIO which takes several dozen CPU cycles. Bit
+  /// durations below roughly 100 CPU cycles could therefore not be emitted reliably.
+  /// # Example
+  /// ```no_run
+  /// # use ruspiro_gpio::*;
+  /// # fn doc() {
+  /// let pin = GPIO.with_mut(|gpio| gpio.get_pin(18)).unwrap().into_output();
+  /// pin.pulse_train(&[true, false, true, true, false], 1_000);
+  /// # }
+  /// ```
+  pub fn pulse_train(&self, bits: &[bool], half_period_cycles: u32) {
+    without_interrupts(|| {
+      for &bit in bits {
+        if bit {
+          self.high();
+        } else {
+          self.low();
+        }
+        wait_cycles(half_period_cycles);
+      }
+    });
+  }
+}
+
+#[cfg(all(test, feature = "mock-mmio"))]
+mod tests {
+  use crate::mock;
+  use crate::Gpio;
+
+  #[test]
+  fn pulse_train_emits_one_write_per_bit() {
+    let _test = crate::exclusive_test();
+    let pin = Gpio::new().get_pin(18).unwrap().into_output();
+    let bits = [true, false, true, true, false];
+    pin.pulse_train(&bits, 10);
+    assert_eq!(mock::pin_history(18), bits);
+    assert_eq!(mock::peek(mock::GPIO_BASE + 0x34) & (1 << 18), 0);
+  }
+
+  #[test]
+  fn square_wave_alternates_and_ends_low() {
+    let _test = crate::exclusive_test();
+    let mut gpio = Gpio::new();
+    let pin = gpio.get_pin(42).unwrap().into_output();
+    gpio.square_wave(&pin, 10, 3, 50);
+    let expected = [true, false, true, false, true, false];
+    assert_eq!(mock::pin_history(42), expected);
+  }
+}